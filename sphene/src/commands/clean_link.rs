@@ -0,0 +1,19 @@
+use poise::CreateReply;
+
+use crate::{Context, Error};
+
+#[poise::command(slash_command, prefix_command)]
+pub async fn clean_link(
+    ctx: Context<'_>,
+    #[description = "The link, or a text containing links, to clean"] link: String,
+) -> Result<(), Error> {
+    let cleaned = ctx.data().registry.clean_links(&link);
+    let content = if cleaned == link {
+        "Nothing to clean 👍".to_string()
+    } else {
+        cleaned
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
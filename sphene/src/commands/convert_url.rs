@@ -1,74 +1,192 @@
+use std::collections::BTreeMap;
+
 use chrono::Datelike;
 use poise::serenity_prelude::{CreateSelectMenuOption, Message};
 use thorium::db::DBConn;
+use thorium::links::{find_links, rewrite_links, Link};
+use thorium::provider::{Provider, ProviderRegistry};
 use tokio::sync::Mutex;
 
+use thorium::{fediverse, twitter};
 
-
-use thorium::{bluesky, instagram, tiktok, twitter};
-
-use crate::{
-    options::{
-        get_blueksy_options, get_instagram_options, get_tik_tok_options, get_twitter_options,
-    },
-};
+use crate::options::get_provider_options;
 
 pub struct ConvertedUrl {
     pub url: String,
+    /// Options of the select menu of every converted link
+    pub menus: Vec<LinkMenu>,
+}
+
+pub struct LinkMenu {
+    /// Key of the post the link points to, see [`menu_id`]
+    pub key: String,
+    /// The post the link points to
+    pub link: String,
     pub options: Vec<CreateSelectMenuOption>,
 }
 
-pub async fn convert_url(msg: Message, dbconn: &Mutex<DBConn>, id: u64, lang: &str) -> Option<ConvertedUrl> {
-    let url: String;
+/// Custom id of the select menu of the post with the key, see [`ProviderRegistry::post_key`].
+/// Media only options add links to the message, so the position of the link can't identify it.
+pub fn menu_id(key: &str) -> String {
+    format!("{}{}", MENU_PREFIX, key)
+}
+
+/// Which link of the message a select menu belongs to
+pub enum MenuTarget<'a> {
+    /// Link to the post with the key
+    Post(&'a str),
+    /// Link at the position, from menus made before posts had keys
+    Index(usize),
+    /// First supported link, from menus made before there were several
+    First,
+}
+
+pub fn menu_target(custom_id: &str) -> MenuTarget<'_> {
+    match custom_id.strip_prefix(MENU_PREFIX) {
+        Some(key) => match key.parse() {
+            Ok(index) => MenuTarget::Index(index),
+            Err(_) => MenuTarget::Post(key),
+        },
+        None => MenuTarget::First,
+    }
+}
+
+const MENU_PREFIX: &str = "select_";
+
+pub async fn convert_url(
+    msg: Message,
+    dbconn: &Mutex<DBConn>,
+    registry: &ProviderRegistry,
+    id: u64,
+    lang: &str,
+) -> Option<ConvertedUrl> {
     let content = msg.content.clone();
-    let options: Vec<CreateSelectMenuOption>;
+    let server = dbconn.lock().await.get_server(id, false);
+
+    // Links no provider supports are only looked at when the server asked for it
+    let fallback = registry
+        .fallback()
+        .filter(|fallback| server.is_enabled(fallback.id()));
 
     // Twitter support should be forcefully disabled starting in April
-    if twitter::is_twitter_url(content.as_str())
-        && (chrono::Utc::now().month() < 4 && chrono::Utc::now().year() == 2025)
-        && dbconn.lock().await.get_server(id, false).twitter
-    {
-        url = twitter::remove_tracking(
-            twitter::convert_url_lazy(content, twitter::UrlType::Vxtwitter).await,
-        );
-
-        options = get_twitter_options(lang);
-    } else if bluesky::is_bluesky_url(content.as_str())
-        && dbconn.lock().await.get_server(id, false).bluesky
-    {
-        url = bluesky::convert_url_lazy(content, bluesky::UrlType::FixBluesky).await;
-        options = get_blueksy_options(lang);
-    } else if tiktok::is_tiktok_url(content.as_str())
-        && dbconn.lock().await.get_server(id, false).tiktok
-    {
-        url = tiktok::convert_url_lazy(tiktok::clear_url(content).await, tiktok::UrlType::TIKTXK)
-            .await;
-        options = get_tik_tok_options(lang);
-    } else if instagram::is_instagram_url(content.as_str())
-        && dbconn.lock().await.get_server(id, false).instagram
-    {
-        url = instagram::convert_url_lazy(content, instagram::UrlType::DDInstagram).await;
-        options = get_instagram_options(lang);
-    } else {
+    let twitter_supported = chrono::Utc::now().month() < 4 && chrono::Utc::now().year() == 2025;
+    let providers: Vec<&dyn Provider> = registry
+        .iter()
+        .filter(|provider| {
+            server.is_enabled(provider.id()) && (provider.id() != twitter::ID || twitter_supported)
+        })
+        .collect();
+
+    // Every link gets its own provider, suppressed links are left as their author wanted them
+    let mut detected = BTreeMap::new();
+    let mut menus: Vec<LinkMenu> = Vec::new();
+    // Whether a link of another website got a menu, which is reason enough to repost the message
+    let mut generic_menu = false;
+    for link in find_links(&content) {
+        if link.suppressed {
+            continue;
+        }
+        let (provider, is_fallback) = match find_provider(&providers, &link, registry, dbconn, id).await {
+            Some(provider) => (provider, false),
+            // Links of disabled providers don't count as unsupported. Only hosts already known
+            // to be Fediverse instances are left out, so disabled providers never cause requests.
+            None => match fallback.filter(|fallback| fallback.detect(&link.url)) {
+                Some(fallback) if registry.find(&link.url).is_none() => (fallback, true),
+                _ => continue,
+            },
+        };
+
+        // Short links only reveal the post they point to by redirecting to it
+        let post = match provider.resolve(registry.client(), &link.url).await {
+            Ok(post) => post,
+            Err(why) => {
+                println!("Error resolving {}: {}", link.url, why);
+                continue;
+            }
+        };
+        let post = registry.clean(&post);
+        // The same post linked twice is edited through a single menu, custom ids have to be unique
+        if let Some(key) = registry.post_key(&post).await {
+            if menus.iter().all(|menu| menu.key != key) {
+                generic_menu |= is_fallback;
+                menus.push(LinkMenu {
+                    key,
+                    link: post.to_string(),
+                    options: get_provider_options(provider, registry, lang),
+                });
+            }
+        }
+        // Other websites only get a menu to download their media, their links are left as they are
+        if !is_fallback {
+            detected.insert(link.range.start, (provider, link, post));
+        }
+    }
+    // Without a mirror to embed the post, its media is linked next to the message
+    let mut media = Vec::new();
+    for (provider, link, post) in detected.values() {
+        if registry.preferred_mirror(*provider).is_some() {
+            continue;
+        }
+        if let Ok(Some(bundle)) = registry.get_media(post).await {
+            // Media of spoilered links stays hidden
+            media.extend(bundle.urls().map(|url| {
+                if link.spoiler {
+                    format!("||{}||", url)
+                } else {
+                    url.to_string()
+                }
+            }));
+        }
+    }
+
+    let mut url = rewrite_links(&content, |link| {
+        let (provider, _, post) = detected.get(&link.range.start)?;
+        Some(match registry.preferred_mirror(*provider) {
+            Some(mirror) => provider.rewrite(post, mirror).to_string(),
+            None => provider.canonicalize(post).to_string(),
+        })
+    });
+    // Links that only needed to be normalized are still worth fixing
+    if url == content && media.is_empty() && !generic_menu {
         return None;
     }
-    
-    Some(ConvertedUrl { url, options })
+    if !media.is_empty() {
+        url = format!("{}\n{}", url, media.join("\n"));
+    }
+
+    Some(ConvertedUrl { url, menus })
 }
 
-pub async fn convert_twitter_to(url: String, kind: &str) -> String {
-    let twitter_urltype = match kind {
-        "direct_vx" => twitter::UrlType::Vxtwitter,
-        "direct_fx" => twitter::UrlType::Fxtwitter,
-        _ => twitter::UrlType::Unknown,
-    };
-
-    let new_msg =
-        twitter::convert_url_lazy(url.to_string(), twitter_urltype).await;
-    
-    format!(
-        "<{}> ({})",
-        new_msg,
-        twitter::get_media_from_url(new_msg.clone()).await
-    )
-}
\ No newline at end of file
+/// First enabled provider that the link points to
+async fn find_provider<'a>(
+    providers: &[&'a dyn Provider],
+    link: &Link,
+    registry: &ProviderRegistry,
+    dbconn: &Mutex<DBConn>,
+    id: u64,
+) -> Option<&'a dyn Provider> {
+    for provider in providers.iter().filter(|provider| provider.detect(&link.url)) {
+        if is_instance_allowed(*provider, link.url.host_str(), dbconn, id).await
+            && provider.verify(registry.client(), &link.url).await
+        {
+            return Some(*provider);
+        }
+    }
+    None
+}
+
+/// Fediverse instances can be allowed or denied per server
+async fn is_instance_allowed(
+    provider: &dyn Provider,
+    host: Option<&str>,
+    dbconn: &Mutex<DBConn>,
+    id: u64,
+) -> bool {
+    if provider.id() != fediverse::ID {
+        return true;
+    }
+    match host {
+        Some(host) => dbconn.lock().await.is_instance_allowed(id, host),
+        None => false,
+    }
+}
@@ -0,0 +1,35 @@
+use thorium::links;
+
+use crate::{utils::get_relevant_id, Context, Error};
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum InstanceAction {
+    Allow,
+    Deny,
+    Reset,
+}
+
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn fediverse_instance(
+    ctx: Context<'_>,
+    #[description = "Should posts from the instance be allowed, denied or should the rule be removed?"] action: InstanceAction,
+    #[description = "Host of the instance, e.g. mastodon.social"] host: String,
+) -> Result<(), Error> {
+    // Accept pasted links as well as bare hosts
+    let host = links::parse(&host)
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or(host)
+        .trim()
+        .to_lowercase();
+
+    let db = ctx.data().db.lock().await;
+    let id = get_relevant_id(ctx);
+    match action {
+        InstanceAction::Allow => db.set_instance(id, &host, true),
+        InstanceAction::Deny => db.set_instance(id, &host, false),
+        InstanceAction::Reset => db.remove_instance(id, &host),
+    }
+    ctx.say(format!("Changed {} to {:#?} 👍", host, action))
+        .await?;
+    Ok(())
+}
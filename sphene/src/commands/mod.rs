@@ -1,3 +1,5 @@
 pub mod set_lang;
 pub mod change;
-pub mod convert_url;
\ No newline at end of file
+pub mod convert_url;
+pub mod fediverse;
+pub mod clean_link;
@@ -1,8 +1,15 @@
+use std::sync::Arc;
+
 use poise::serenity_prelude as serenity;
-use thorium::db::{DBConn};
+use thorium::cache::{CacheConfig, MediaCache};
+use thorium::db::{DBConn, DB_PATH};
+use thorium::provider::ProviderRegistry;
+use thorium::{ThoriumClient, ThoriumConfig};
 use tokio::sync::Mutex;
 use commands::set_lang::{set_guild_language, set_own_language};
 use commands::change::change;
+use commands::fediverse::fediverse_instance;
+use commands::clean_link::clean_link;
 
 
 
@@ -10,6 +17,7 @@ rust_i18n::i18n!("../locales", fallback = "en");
 
 struct Data {
     db: Mutex<DBConn>,
+    registry: Arc<ProviderRegistry>,
 } // User data, which is stored and accessible in all command invocations
 type Error = Box<dyn std::error::Error + Send + Sync>;
 pub(crate) type Context<'a> = poise::Context<'a, Data, Error>;
@@ -26,12 +34,23 @@ async fn main() {
     let token = std::env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
     let intents = serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
 
+    let config = ThoriumConfig::load().expect("invalid thorium config");
+    let http_client = ThoriumClient::new(config.client_config()).expect("failed to build the HTTP client");
+    let cache = MediaCache::with_database(CacheConfig::default(), DB_PATH).unwrap();
+    let registry = Arc::new(
+        ProviderRegistry::with_builtin_providers(http_client, &config).with_cache(cache),
+    );
+
+    // Keep track of which mirrors are down so that links aren't rewritten to them
+    let monitored_registry = registry.clone();
+    tokio::spawn(async move { monitored_registry.monitor_mirrors().await });
+
     let dbconn = Mutex::new(DBConn::new().unwrap());
     dbconn.lock().await.create_new();
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![change(), set_own_language(), set_guild_language()],
+            commands: vec![change(), set_own_language(), set_guild_language(), fediverse_instance(), clean_link()],
             event_handler: |ctx, event, framework, data| { 
                 Box::pin(message_handler::event_handler(ctx, event, framework, data))   
             },
@@ -40,7 +59,10 @@ async fn main() {
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data { db: dbconn })
+                Ok(Data {
+                    db: dbconn,
+                    registry,
+                })
             })
         })
         .build();
@@ -16,10 +16,10 @@ pub async fn event_handler(
             println!("Logged in as {}", data_about_bot.user.name);
         }
         FullEvent::Message { new_message } => {
-            message_event::message(ctx, new_message.clone(), &data.db).await;
+            message_event::message(ctx, new_message.clone(), &data.db, &data.registry).await;
         }
         FullEvent::InteractionCreate { interaction: Interaction::Component(component) } => {
-            interaction_event::interaction_create(ctx, component.clone(), &data.db).await;
+            interaction_event::interaction_create(ctx, component.clone(), &data.db, &data.registry).await;
         }
         _ => {}
     }
@@ -1,15 +1,27 @@
-
+use std::ops::Range;
 
 use poise::serenity_prelude::{
-    ComponentInteraction, ComponentInteractionDataKind, Context, CreateAllowedMentions, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditInteractionResponse, EditMessage
+    ComponentInteraction, ComponentInteractionDataKind, Context, CreateAllowedMentions, CreateAttachment, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditInteractionResponse, EditMessage
 };
 use rust_i18n::t;
-use thorium::{bluesky, db::DBConn, instagram, tiktok, twitter};
+use thorium::{db::DBConn, download::{download_media, DownloadLimits}, links::{find_links, Link}, mosaic::{compose, MosaicConfig}, provider::ProviderRegistry, ThoriumError};
 use tokio::sync::Mutex;
 
-use crate::{commands::convert_url::convert_twitter_to, utils::REGEX_URL_EXTRACTOR};
+use crate::{
+    commands::convert_url::{menu_target, MenuTarget},
+    options::{DIRECT_PREFIX, ORIGINAL_OPTION},
+    utils::{describe_media, upload_limit},
+};
+
+/// Most files Discord accepts in a single message
+const MAX_ATTACHMENTS: usize = 10;
 
-pub async fn interaction_create(ctx: &Context, component: ComponentInteraction, dbconn: &Mutex<DBConn>) {
+pub async fn interaction_create(
+    ctx: &Context,
+    component: ComponentInteraction,
+    dbconn: &Mutex<DBConn>,
+    registry: &ProviderRegistry,
+) {
     let command = match &component.data.kind {
         ComponentInteractionDataKind::StringSelect { values, .. } => values[0].as_str(),
         _ => return ,
@@ -21,8 +33,6 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
         return;
     }
 
-    let regex = regex::Regex::new(REGEX_URL_EXTRACTOR).unwrap();
-
     // Get user id
     let id = msg.author.id.get();
     let get_lang = match dbconn.lock().await.get_server(id, false).language {
@@ -37,6 +47,19 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
     .await
     .unwrap();
 
+    // The link the menu belongs to
+    let link = match menu_target(&component.data.custom_id) {
+        MenuTarget::Post(key) => registry.find_post_link(&msg.content, key).await,
+        MenuTarget::Index(index) => find_links(&msg.content).into_iter().nth(index),
+        MenuTarget::First => find_links(&msg.content)
+            .into_iter()
+            .find(|link| registry.find(&link.url).is_some()),
+    };
+    let target = match link {
+        Some(link) => registry.find_or_fallback(&link.url).await.map(|provider| (provider, link)),
+        None => None,
+    };
+
     let user = &component.user.id.to_string();
     // Check whether user is correct
     if !msg.content.contains(user)
@@ -47,6 +70,10 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
         || command == "set_language"
         || command == "contribute_language"
     {
+        let mut files = Vec::new();
+        // Links to the attached media, in case Discord refuses the files, and to the media that couldn't be attached
+        let mut attached = Vec::new();
+        let mut unattached = Vec::new();
         let content = if command == "version" {
             t!(
                 "source_code",
@@ -66,48 +93,70 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
             )
             .to_string()
         } else if command == "download" {
-            let extracted_url = regex
-                .find_iter(&msg.content)
-                .next()
-                .unwrap()
-                .as_str()
-                .to_string();
-
-            let url = if twitter::UrlType::from_string(&extracted_url)
-                != twitter::UrlType::Unknown
-            {
-                twitter::get_media_from_url(
-                    twitter::convert_url_lazy(extracted_url, twitter::UrlType::Vxtwitter).await,
-                )
-                .await
-            } else if tiktok::UrlType::from_string(&extracted_url) != tiktok::UrlType::Unknown {
-                tiktok::get_media_from_url(
-                    tiktok::convert_url_lazy(extracted_url, tiktok::UrlType::TIKTXK).await,
-                )
-                .await
-            } else {
-                bluesky::get_media_from_url(
-                    bluesky::convert_url_lazy(extracted_url, bluesky::UrlType::FixBluesky)
-                        .await,
-                )
-                .await
+            let media = match &target {
+                Some((_, link)) => registry.get_media_or_fallback(&link.url).await,
+                None => Err(ThoriumError::UnsupportedUrl(msg.content.clone())),
             };
 
-            if url != "0" {
-                t!("download_url", locale = lang, URL = url).to_string()
-            } else {
-                t!("no_download", locale = lang).to_string()
+            match media {
+                Ok(Some(bundle)) => {
+                    // Everything attached to a message shares the upload limit of the server
+                    let tier = component
+                        .guild_id
+                        .and_then(|id| id.to_guild_cached(&ctx.cache).map(|guild| guild.premium_tier))
+                        .unwrap_or_default();
+                    let mut budget = upload_limit(tier);
+
+                    // Media that can't be attached is linked instead
+                    for item in &bundle.items {
+                        let link = t!("download_url", locale = lang, URL = item.url).to_string();
+                        if files.len() >= MAX_ATTACHMENTS {
+                            unattached.push(link);
+                            continue;
+                        }
+                        match download_media(registry.client(), item, &DownloadLimits::new(budget)).await {
+                            // Playlists are useless without the segments they list
+                            Ok(download) if download.extension == "m3u8" => unattached.push(link),
+                            Ok(download) => {
+                                budget -= download.size;
+                                let file_name = download.file_name.clone();
+                                files.push(CreateAttachment::bytes(download.into_bytes().unwrap_or_default(), file_name));
+                                attached.push(link);
+                            }
+                            Err(why) => unattached.push(format!("{}\n{}", link, describe_media(&Err(why), lang))),
+                        }
+                    }
+
+                    if unattached.is_empty() {
+                        t!("download_attached", locale = lang).to_string()
+                    } else {
+                        unattached.join("\n")
+                    }
+                }
+                _ => describe_media(&media, lang),
             }
         } else {
             t!("not_author", locale = lang).to_string()
         };
         
-        let response = CreateInteractionResponseFollowup::new().content(content).ephemeral(true);
+        let response = CreateInteractionResponseFollowup::new().content(content.clone()).ephemeral(true).add_files(files);
 
-        component
-            .create_followup(&ctx.http, response)
-            .await
-            .unwrap();
+        if let Err(why) = component.create_followup(&ctx.http, response).await {
+            println!("Error sending followup: {:?}", why);
+
+            // Without the files, the media is linked so the user still gets it
+            let mut lines = vec![t!("upload_failed", locale = lang, WHY = why).to_string()];
+            if attached.is_empty() {
+                lines.push(content);
+            } else {
+                lines.extend(attached);
+                lines.extend(unattached);
+            }
+            let response = CreateInteractionResponseFollowup::new().content(lines.join("\n")).ephemeral(true);
+            if let Err(why) = component.create_followup(&ctx.http, response).await {
+                println!("Error sending followup: {:?}", why);
+            }
+        }
 
         return;
     } 
@@ -137,59 +186,54 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
             println!("Error deleting message: {:?}", why);
         }
     } else {
-        let extracted_url = regex
-            .find_iter(&msg.content)
-            .next()
-            .unwrap()
-            .as_str()
-            .to_string();
-        let mut new_msg: String = String::new();
-
-        let twitter_urltype = twitter::UrlType::from_string(command);
-        let bluesky_urltype = bluesky::UrlType::from_string(command);
-        let instagram_urltype = instagram::UrlType::from_string(command);
-        let tiktok_urltype = tiktok::UrlType::from_string(command);
-
-        if twitter_urltype != twitter::UrlType::Unknown {
-            new_msg = twitter::convert_url_lazy(extracted_url, twitter_urltype).await;
-        } else if bluesky_urltype != bluesky::UrlType::Unknown {
-            new_msg = bluesky::convert_url_lazy(extracted_url, bluesky_urltype).await;
-        } else if instagram_urltype != instagram::UrlType::Unknown {
-            new_msg = instagram::convert_url_lazy(extracted_url, instagram_urltype).await;
-        } else if tiktok_urltype != tiktok::UrlType::Unknown {
-            new_msg = tiktok::convert_url_lazy(extracted_url, tiktok_urltype).await;
-        } else if command == "direct_vx" || command == "direct_fx" {
-            new_msg = convert_twitter_to(
-                extracted_url.to_string(),
-                command,
-            ).await
-        } else if command == "direct_fxbsky" {
-            new_msg = bluesky::convert_url_lazy(
-                extracted_url.to_string(),
-                bluesky::UrlType::FixBluesky,
-            )
-            .await;
-            new_msg = format!(
-                "<{}> ({})",
-                new_msg,
-                bluesky::get_media_from_url(new_msg.clone()).await
-            );
-        } else if command == "direct_tiktxk" {
-            new_msg =
-                tiktok::convert_url_lazy(extracted_url.to_string(), tiktok::UrlType::TIKTXK)
-                    .await;
-            new_msg = format!(
-                "<{}> ({})",
-                new_msg,
-                tiktok::get_media_from_url(new_msg.clone()).await
-            );
-        }
+        let mut new_msg = msg.content.clone();
+        let mut mosaic = None;
+
+        if let Some((provider, link)) = &target {
+            let replacement = if command == ORIGINAL_OPTION {
+                Some(provider.canonicalize(&link.url).to_string())
+            } else if let Some(id) = command.strip_prefix(DIRECT_PREFIX) {
+                // Providers without mirrors are scraped on the original site
+                let url = match provider.mirror(id) {
+                    Some(mirror) => provider.rewrite(&link.url, mirror),
+                    None => provider.canonicalize(&link.url),
+                };
+                let media = registry.get_media_or_fallback(&url).await;
+                // Galleries are combined here instead of relying on a mirror to do it
+                mosaic = match &media {
+                    Ok(Some(bundle)) => compose(registry.client(), bundle, &MosaicConfig::default())
+                        .await
+                        .unwrap_or_else(|why| {
+                            println!("Error composing mosaic: {}", why);
+                            None
+                        }),
+                    _ => None,
+                };
+                let description = match &mosaic {
+                    Some(_) => t!("mosaic_attached", locale = lang).to_string(),
+                    None => describe_media(&media, lang),
+                };
+                Some(format!("<{}> ({})", url, description))
+            } else {
+                provider
+                    .mirror(command)
+                    .map(|mirror| provider.rewrite(&link.url, mirror).to_string())
+            };
 
-        new_msg = format!("<@{}>: {}", user, new_msg);
+            // Only the link of the menu changes, the rest of the message stays as it is
+            if let Some(replacement) = replacement {
+                new_msg.replace_range(replaced_range(&msg.content, link), &replacement);
+            }
+        }
 
-        let edit_message = EditMessage::new()
+        let mut edit_message = EditMessage::new()
             .content(new_msg)
-            .allowed_mentions(CreateAllowedMentions::new().empty_roles().empty_users());
+            .allowed_mentions(CreateAllowedMentions::new().empty_roles().empty_users())
+            .remove_all_attachments();
+        if let Some(mosaic) = mosaic {
+            let file_name = mosaic.file_name();
+            edit_message = edit_message.new_attachment(CreateAttachment::bytes(mosaic.data, file_name));
+        }
 
         if let Err(why) = msg.to_owned()
             .edit(&ctx.http,  edit_message)
@@ -200,4 +244,29 @@ pub async fn interaction_create(ctx: &Context, component: ComponentInteraction,
 
         component.delete_response(&ctx.http).await.unwrap();
     }
-}
\ No newline at end of file
+}
+
+/// Range of the link, including the brackets and the description a direct media option put around it
+fn replaced_range(content: &str, link: &Link) -> Range<usize> {
+    if !link.suppressed {
+        return link.range.clone();
+    }
+
+    let start = link.range.start - 1;
+    let mut end = link.range.end + 1;
+    if content[end..].starts_with(" (") {
+        let mut depth = 0;
+        for (offset, char) in content[end + 1..].char_indices() {
+            match char {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                end += 1 + offset + char.len_utf8();
+                break;
+            }
+        }
+    }
+    start..end
+}
@@ -6,19 +6,28 @@ use poise::serenity_prelude::{
     MessageBuilder, UserId,
 };
 use rust_i18n::t;
-use thorium::{db::DBConn};
+use thorium::{db::DBConn, provider::ProviderRegistry};
 use tokio::sync::Mutex;
-use rand::seq::{IndexedRandom, SliceRandom};
+use rand::seq::IndexedRandom;
 
 use crate::{
-    commands::convert_url::convert_url
+    commands::convert_url::{convert_url, menu_id}
 };
 
-pub async fn message(context: &Context, msg: Message, dbconn: &Mutex<DBConn>) {
-    let id = if msg.guild_id.is_some() {
-        msg.guild_id.unwrap().get()
-    } else {
-        msg.author.id.get()
+/// Discord only shows this many rows of components below a message
+const MAX_ACTION_ROWS: usize = 5;
+/// Longest placeholder Discord accepts for a select menu
+const MAX_PLACEHOLDER_LENGTH: usize = 150;
+
+pub async fn message(
+    context: &Context,
+    msg: Message,
+    dbconn: &Mutex<DBConn>,
+    registry: &ProviderRegistry,
+) {
+    let id = match msg.guild_id {
+        Some(guild_id) => guild_id.get(),
+        None => msg.author.id.get(),
     };
 
     let get_lang = match dbconn.lock().await.get_server(id, false).language {
@@ -27,7 +36,7 @@ pub async fn message(context: &Context, msg: Message, dbconn: &Mutex<DBConn>) {
     };
     let lang = get_lang.as_str();
 
-    let converted_url = convert_url(msg.clone(), dbconn, id, lang).await;
+    let converted_url = convert_url(msg.clone(), dbconn, registry, id, lang).await;
 
     if converted_url.is_none() && msg.referenced_message.is_none() {
         return;
@@ -78,7 +87,7 @@ pub async fn message(context: &Context, msg: Message, dbconn: &Mutex<DBConn>) {
 
     let converted_url = converted_url.unwrap();
     let url = converted_url.url;
-    let options = converted_url.options;
+    let menus = converted_url.menus;
 
     const NEWS_URLS: [&str; 5] = [
         "https://www.nbcnews.com/news/world/elon-musks-call-germany-move-nazi-guilt-dangerous-holocaust-memorial-c-rcna189316",
@@ -114,21 +123,35 @@ pub async fn message(context: &Context, msg: Message, dbconn: &Mutex<DBConn>) {
         message = message.reference_message(msg.message_reference.clone().unwrap());
     };
 
-    let select_menu =
-        CreateSelectMenu::new("select", CreateSelectMenuKind::String { options })
+    // Every converted link gets its own menu, as far as Discord allows
+    let several = menus.len() > 1;
+    let action_rows = menus
+        .into_iter()
+        .take(MAX_ACTION_ROWS)
+        .map(|menu| {
+            let placeholder = if several {
+                menu.link.chars().take(MAX_PLACEHOLDER_LENGTH).collect()
+            } else {
+                t!("nothing_selected", locale = lang).to_string()
+            };
+            let select_menu = CreateSelectMenu::new(
+                menu_id(&menu.key),
+                CreateSelectMenuKind::String { options: menu.options },
+            )
             .max_values(1)
             .min_values(1)
-            .placeholder(t!("nothing_selected", locale = lang));
-
-    let action_row = CreateActionRow::SelectMenu(select_menu);
+            .placeholder(placeholder);
+            CreateActionRow::SelectMenu(select_menu)
+        })
+        .collect();
 
-    message = message.components(vec![action_row]);
+    message = message.components(action_rows);
 
     if let Err(why) = msg.channel_id.send_message(&context.http, message).await {
         println!("{}", t!("error_sending_message", locale = lang, WHY = why));
     };
 
-    if !msg.is_private() {
+    if msg.guild_id.is_some() {
         // Delete message
         if let Err(why) = msg.delete(&context.http).await {
             println!("{}", t!("error_delete_message", locale = lang, WHY = why));
@@ -1,10 +1,15 @@
 use poise::serenity_prelude::CreateSelectMenuOption;
 
 use rust_i18n::t;
-use thorium::{bluesky, instagram, tiktok, twitter};
+use thorium::{bluesky, fediverse, generic, instagram, pixiv, provider::{Provider, ProviderRegistry}, reddit, threads, tiktok, tumblr, twitch, twitter, youtube};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Value of the option that rewrites the link back to the original site
+pub const ORIGINAL_OPTION: &str = "original";
+/// Prefix of options that show the media of the link through a mirror
+pub const DIRECT_PREFIX: &str = "direct_";
+
 rust_i18n::i18n!("locales", fallback = "en");
 
 pub fn get_remove_option(lang: &str) -> CreateSelectMenuOption {
@@ -37,16 +42,37 @@ pub fn get_version_option(lang: &str) -> CreateSelectMenuOption {
     CreateSelectMenuOption::new(t!("version", locale = lang, VERSION = VERSION), "version")
 }
 
-pub fn get_blueksy_options(lang: &str) -> Vec<CreateSelectMenuOption> {
+fn get_direct_value(mirror: &str) -> String {
+    format!("{}{}", DIRECT_PREFIX, mirror)
+}
+
+/// Options that go through a mirror, left out while the mirror is down
+fn get_mirror_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    options: Vec<(&str, CreateSelectMenuOption)>,
+) -> Vec<CreateSelectMenuOption> {
+    options
+        .into_iter()
+        .filter(|(id, _)| {
+            provider
+                .mirror(id)
+                .is_some_and(|mirror| registry.is_healthy(mirror))
+        })
+        .map(|(_, option)| option)
+        .collect()
+}
+
+/// Options shown for every provider after its own ones
+fn get_common_options(lang: &str, original_label: impl Into<String>) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![CreateSelectMenuOption::new(original_label, ORIGINAL_OPTION)];
+    options.extend(get_message_options(lang));
+    options
+}
+
+/// Options that leave the link itself alone
+fn get_message_options(lang: &str) -> Vec<CreateSelectMenuOption> {
     vec![
-        get_download_option(lang),
-        CreateSelectMenuOption::new(t!("psky", locale = lang), bluesky::PSKY_URL),
-        CreateSelectMenuOption::new(t!("fixbluesky", locale = lang), bluesky::FIXBLUESKY_URL),
-        CreateSelectMenuOption::new(t!("media_only", locale = lang), "direct_fxbsky"),
-        CreateSelectMenuOption::new(
-            t!("show_original_bluesky", locale = lang),
-            bluesky::BLUESKY_URL,
-        ),
         get_remove_option(lang),
         get_set_language_option(lang),
         get_contribute_language_option(lang),
@@ -56,57 +82,175 @@ pub fn get_blueksy_options(lang: &str) -> Vec<CreateSelectMenuOption> {
     ]
 }
 
-pub fn get_twitter_options(lang: &str) -> Vec<CreateSelectMenuOption> {
-    vec![
+pub fn get_provider_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    match provider.id() {
+        twitter::ID => get_twitter_options(provider, registry, lang),
+        bluesky::ID => get_blueksy_options(provider, registry, lang),
+        instagram::ID => get_instagram_options(provider, registry, lang),
+        tiktok::ID => get_tik_tok_options(provider, registry, lang),
+        reddit::ID => get_reddit_options(provider, registry, lang),
+        threads::ID => get_threads_options(provider, registry, lang),
+        pixiv::ID => get_pixiv_options(provider, registry, lang),
+        tumblr::ID => get_tumblr_options(provider, registry, lang),
+        twitch::ID => get_twitch_options(lang),
+        youtube::ID => get_youtube_options(lang),
+        fediverse::ID => get_fediverse_options(lang),
+        generic::ID => get_generic_options(lang),
+        _ => Vec::new(),
+    }
+}
+
+pub fn get_blueksy_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (bluesky::PSKY, CreateSelectMenuOption::new(t!("psky", locale = lang), bluesky::PSKY)),
+        (bluesky::FIXBLUESKY, CreateSelectMenuOption::new(t!("fixbluesky", locale = lang), bluesky::FIXBLUESKY)),
+        (bluesky::FIXBLUESKY, CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(bluesky::FIXBLUESKY))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_bluesky", locale = lang)));
+    options
+}
+
+pub fn get_twitter_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (twitter::VXTWITTER, CreateSelectMenuOption::new(t!("vxtwitter", locale = lang), twitter::VXTWITTER)),
+        (twitter::FXTWITTER, CreateSelectMenuOption::new(t!("fxtwitter", locale = lang), twitter::FXTWITTER)),
+        (twitter::VXTWITTER, CreateSelectMenuOption::new(t!("media_only_vxtwitter", locale = lang), get_direct_value(twitter::VXTWITTER))),
+        (twitter::FXTWITTER, CreateSelectMenuOption::new(t!("media_only_fxtwitter", locale = lang), get_direct_value(twitter::FXTWITTER))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_twitter", locale = lang)));
+    options
+}
+
+pub fn get_instagram_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (instagram::DDINSTAGRAM, CreateSelectMenuOption::new(t!("ddinstagram", locale = lang), instagram::DDINSTAGRAM)),
+        (instagram::DDINSTAGRAM, CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(instagram::DDINSTAGRAM))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_instagram", locale = lang)));
+    options
+}
+
+pub fn get_tik_tok_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (tiktok::TIKTXK, CreateSelectMenuOption::new(t!("tiktxk", locale = lang), tiktok::TIKTXK)),
+        (tiktok::TNKTOK, CreateSelectMenuOption::new(t!("tnktok", locale = lang), tiktok::TNKTOK)),
+        (tiktok::TIKTXK, CreateSelectMenuOption::new(t!("show_media_only_tiktok", locale = lang), get_direct_value(tiktok::TIKTXK))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_tiktok", locale = lang)));
+    options
+}
+
+pub fn get_reddit_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (reddit::RXDDIT, CreateSelectMenuOption::new(t!("rxddit", locale = lang), reddit::RXDDIT)),
+        (reddit::VXREDDIT, CreateSelectMenuOption::new(t!("vxreddit", locale = lang), reddit::VXREDDIT)),
+        (reddit::RXDDIT, CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(reddit::RXDDIT))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_reddit", locale = lang)));
+    options
+}
+
+pub fn get_threads_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (threads::FIXTHREADS, CreateSelectMenuOption::new(t!("fixthreads", locale = lang), threads::FIXTHREADS)),
+        (threads::VXTHREADS, CreateSelectMenuOption::new(t!("vxthreads", locale = lang), threads::VXTHREADS)),
+        (threads::FIXTHREADS, CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(threads::FIXTHREADS))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_threads", locale = lang)));
+    options
+}
+
+pub fn get_pixiv_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (pixiv::PHIXIV, CreateSelectMenuOption::new(t!("phixiv", locale = lang), pixiv::PHIXIV)),
+        (pixiv::PHIXIV, CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(pixiv::PHIXIV))),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_pixiv", locale = lang)));
+    options
+}
+
+/// The mirror is only offered when one is configured, media only then scrapes Tumblr itself
+pub fn get_tumblr_options(
+    provider: &dyn Provider,
+    registry: &ProviderRegistry,
+    lang: &str,
+) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![
         get_download_option(lang),
-        CreateSelectMenuOption::new(t!("vxtwitter", locale = lang), twitter::VXTWITTER_URL),
-        CreateSelectMenuOption::new(t!("fxtwitter", locale = lang), twitter::FXTWITTER_URL),
-        CreateSelectMenuOption::new(t!("media_only_vxtwitter", locale = lang), "direct_vx"),
-        CreateSelectMenuOption::new(t!("media_only_fxtwitter", locale = lang), "direct_fx"),
-        CreateSelectMenuOption::new(
-            t!("show_original_twitter", locale = lang),
-            twitter::TWITTER_URL,
-        ),
-        get_remove_option(lang),
-        get_set_language_option(lang),
-        get_contribute_language_option(lang),
-        get_disable_option(lang),
-        get_version_option(lang),
-        get_default_option(lang),
-    ]
+        CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(tumblr::FXTUMBLR)),
+    ];
+    options.extend(get_mirror_options(provider, registry, vec![
+        (tumblr::FXTUMBLR, CreateSelectMenuOption::new(t!("fxtumblr", locale = lang), tumblr::FXTUMBLR)),
+    ]));
+    options.extend(get_common_options(lang, t!("show_original_tumblr", locale = lang)));
+    options
 }
 
-pub fn get_instagram_options(lang: &str) -> Vec<CreateSelectMenuOption> {
-    vec![
-        CreateSelectMenuOption::new(t!("ddinstagram", locale = lang), instagram::DDINSTAGRAM_URL),
-        CreateSelectMenuOption::new(
-            t!("show_original_instagram", locale = lang),
-            instagram::INSTAGRAM_URL,
-        ),
-        get_remove_option(lang),
-        get_set_language_option(lang),
-        get_contribute_language_option(lang),
-        get_disable_option(lang),
-        get_version_option(lang),
-        get_default_option(lang),
-    ]
+/// Clips embed on their own, the bot only adds their video
+pub fn get_twitch_options(lang: &str) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_common_options(lang, t!("show_original_twitch", locale = lang)));
+    options
 }
 
-pub fn get_tik_tok_options(lang: &str) -> Vec<CreateSelectMenuOption> {
-    vec![
+/// Shorts are only changed to regular links, there is nothing to download
+pub fn get_youtube_options(lang: &str) -> Vec<CreateSelectMenuOption> {
+    get_common_options(lang, t!("show_original_youtube", locale = lang))
+}
+
+/// Posts stay on their instance, there are no mirrors to change to
+pub fn get_fediverse_options(lang: &str) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![
         get_download_option(lang),
-        CreateSelectMenuOption::new(t!("tiktxk", locale = lang), tiktok::TIKTXK_URL),
-        CreateSelectMenuOption::new(t!("tnktok", locale = lang), tiktok::TNKTOK_URL),
-        CreateSelectMenuOption::new(t!("show_media_only_tiktok", locale = lang), "direct_tiktxk"),
-        CreateSelectMenuOption::new(
-            t!("show_original_tiktok", locale = lang),
-            tiktok::TIKTOK_URL,
-        ),
-        get_remove_option(lang),
-        get_set_language_option(lang),
-        get_contribute_language_option(lang),
-        get_disable_option(lang),
-        get_version_option(lang),
-        get_default_option(lang),
-    ]
+        CreateSelectMenuOption::new(t!("media_only", locale = lang), get_direct_value(fediverse::ID)),
+    ];
+    options.extend(get_common_options(lang, t!("show_original_fediverse", locale = lang)));
+    options
+}
+
+/// Options of links to other websites: downloading the media scraped from their page,
+/// and the options about the message, as there is no mirror or original link to switch to
+pub fn get_generic_options(lang: &str) -> Vec<CreateSelectMenuOption> {
+    let mut options = vec![get_download_option(lang)];
+    options.extend(get_message_options(lang));
+    options
 }
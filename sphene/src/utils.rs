@@ -1,5 +1,7 @@
 
-use thorium::db::Server;
+use poise::serenity_prelude::PremiumTier;
+use rust_i18n::t;
+use thorium::{db::Server, download::DEFAULT_MAX_BYTES, MediaBundle, ThoriumError};
 
 use crate::Context;
 
@@ -9,10 +11,17 @@ pub enum Choices {
     Bluesky,
     Instagram,
     Tiktok,
+    Reddit,
+    Threads,
+    Pixiv,
+    Tumblr,
+    Twitch,
+    Youtube,
+    Fediverse,
+    #[name = "Other websites"]
+    Generic,
 }
 
-pub const REGEX_URL_EXTRACTOR: &str = r"\b(?:https?:\/\/|<)[^\s>]+(?:>|)\b";
-
 #[derive(Debug, Clone, poise::ChoiceParameter)]
 pub enum EnableOrDisable {
     Enable = 1,
@@ -57,6 +66,30 @@ pub fn parse_choice(choice: &Choices, mut server: Server, change_to: bool) -> Se
         Choices::Tiktok => {
             server.tiktok = change_to;
         }
+        Choices::Reddit => {
+            server.reddit = change_to;
+        }
+        Choices::Threads => {
+            server.threads = change_to;
+        }
+        Choices::Pixiv => {
+            server.pixiv = change_to;
+        }
+        Choices::Tumblr => {
+            server.tumblr = change_to;
+        }
+        Choices::Twitch => {
+            server.twitch = change_to;
+        }
+        Choices::Youtube => {
+            server.youtube = change_to;
+        }
+        Choices::Fediverse => {
+            server.fediverse = change_to;
+        }
+        Choices::Generic => {
+            server.generic = change_to;
+        }
     }
     server
 }
@@ -69,3 +102,34 @@ pub fn get_relevant_id(ctx: Context<'_>) -> u64 {
         ctx.author().id.get()
     }
 }
+
+// Gets the media urls (or the mosaic combining them), or a localized explanation why there are none
+pub fn describe_media(media: &thorium::Result<Option<MediaBundle>>, lang: &str) -> String {
+    match media {
+        Ok(Some(bundle)) => match &bundle.mosaic {
+            Some(mosaic) => mosaic.clone(),
+            None => bundle.urls().collect::<Vec<_>>().join(" "),
+        },
+        Ok(None) | Err(ThoriumError::NoMedia) => t!("no_download", locale = lang).to_string(),
+        Err(ThoriumError::Network(_) | ThoriumError::Timeout) => t!("media_network_error", locale = lang).to_string(),
+        Err(ThoriumError::Status(status)) => {
+            t!("media_status_error", locale = lang, STATUS = status.as_u16()).to_string()
+        }
+        Err(ThoriumError::Parse(_)) => t!("media_parse_error", locale = lang).to_string(),
+        Err(ThoriumError::UnsupportedUrl(_)) => t!("media_unsupported", locale = lang).to_string(),
+        Err(ThoriumError::TooLarge { limit, .. }) => {
+            t!("media_too_large", locale = lang, LIMIT = limit / 1024 / 1024).to_string()
+        }
+        Err(ThoriumError::Incomplete { .. }) => t!("media_incomplete", locale = lang).to_string(),
+        Err(ThoriumError::Io(_)) => t!("media_io_error", locale = lang).to_string(),
+    }
+}
+
+// Largest file the bot can upload to a server with the given boost level
+pub fn upload_limit(tier: PremiumTier) -> u64 {
+    match tier {
+        PremiumTier::Tier2 => 50 * 1024 * 1024,
+        PremiumTier::Tier3 => 100 * 1024 * 1024,
+        _ => DEFAULT_MAX_BYTES,
+    }
+}
@@ -1,55 +1,237 @@
-pub const BLUESKY_URL: &str = "https://bsky.app/";
-pub const PSKY_URL: &str = "https://psky.app/";
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use url::Url;
+
+use crate::links::path_segments;
+use crate::post::parse_time;
+use crate::provider::{Mirror, Provider};
+use crate::{
+    MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumConfig, ThoriumError,
+};
+
+pub const ID: &str = "bluesky";
+
+pub const BLUESKY_HOST: &str = "bsky.app";
+
+/// Ids of the mirrors
+pub const FIXBLUESKY: &str = "fixbluesky";
+pub const PSKY: &str = "psky";
+/// Id of the AppView serving the API, configured like mirrors
+pub const BLUESKY_APPVIEW: &str = "bsky_appview";
+
 pub const FIXBLUESKY_URL: &str = "https://bsyy.app/";
+pub const PSKY_URL: &str = "https://psky.app/";
+pub const BLUESKY_APPVIEW_URL: &str = "https://public.api.bsky.app/";
+
+static HOSTS: [&str; 2] = [BLUESKY_HOST, "www.bsky.app"];
 
-#[derive(Debug, PartialEq)]
-pub enum UrlType {
-    Bluesky,
-    Psky,
-    FixBluesky,
-    Unknown,
+pub struct Bluesky {
+    mirrors: Vec<Mirror>,
+    appview_url: Url,
+    /// DIDs of handles, by handle
+    dids: Mutex<HashMap<String, String>>,
 }
 
-impl UrlType {
-    pub fn as_str(&self) -> &'static str {
-        match *self {
-            UrlType::Bluesky => BLUESKY_URL,
-            UrlType::Psky => PSKY_URL,
-            UrlType::FixBluesky => FIXBLUESKY_URL,
-            UrlType::Unknown => "",
+impl Bluesky {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![
+                Mirror::new(FIXBLUESKY, FIXBLUESKY_URL, config),
+                Mirror::new(PSKY, PSKY_URL, config),
+            ],
+            appview_url: config.url(BLUESKY_APPVIEW, BLUESKY_APPVIEW_URL),
+            dids: Mutex::default(),
         }
     }
 
-    pub fn from_string(url: &str) -> UrlType {
-        if url.contains(BLUESKY_URL) {
-            return UrlType::Bluesky;
-        } else if url.contains(PSKY_URL) {
-            return UrlType::Psky;
-        } else if url.contains(FIXBLUESKY_URL) {
-            return UrlType::FixBluesky;
+    /// Url of an XRPC method of the AppView with the given parameters
+    fn xrpc_url(&self, method: &str, parameters: &[(&str, &str)]) -> Url {
+        let mut url = self.appview_url.clone();
+        url.set_path(&format!(
+            "{}/xrpc/{}",
+            self.appview_url.path().trim_end_matches('/'),
+            method
+        ));
+        url.query_pairs_mut().extend_pairs(parameters);
+        url
+    }
+
+    /// Handles can change and point to other accounts, the DID of an account never does
+    async fn resolve_did(&self, client: &ThoriumClient, actor: &str) -> Result<String> {
+        if actor.starts_with("did:") {
+            return Ok(actor.to_string());
         }
-        UrlType::Unknown
+        if let Some(did) = self.dids.lock().unwrap().get(actor) {
+            return Ok(did.clone());
+        }
+
+        let url = self.xrpc_url("com.atproto.identity.resolveHandle", &[("handle", actor)]);
+        let response: Value = serde_json::from_str(&client.get_text(url.as_str()).await?)?;
+        let did = response["did"]
+            .as_str()
+            .ok_or_else(|| ThoriumError::Parse(format!("no DID for {}", actor)))?
+            .to_string();
+
+        self.dids
+            .lock()
+            .unwrap()
+            .insert(actor.to_string(), did.clone());
+        Ok(did)
+    }
+
+    /// View of the post from `app.bsky.feed.getPostThread`
+    async fn fetch_post(&self, client: &ThoriumClient, url: &Url) -> Result<Value> {
+        let canonical = self.canonicalize(url);
+        let (actor, rkey) = match path_segments(&canonical)[..] {
+            ["profile", actor, "post", rkey, ..] => (actor.to_string(), rkey.to_string()),
+            _ => return Err(ThoriumError::UnsupportedUrl(url.to_string())),
+        };
+
+        let did = self.resolve_did(client, &actor).await?;
+        let uri = format!("at://{}/app.bsky.feed.post/{}", did, rkey);
+        let url = self.xrpc_url(
+            "app.bsky.feed.getPostThread",
+            &[("uri", &uri), ("depth", "0"), ("parentHeight", "0")],
+        );
+        let response: Value = serde_json::from_str(&client.get_text(url.as_str()).await?)?;
+
+        let post = &response["thread"]["post"];
+        if !post.is_object() {
+            // Deleted and blocked posts come as a thread without a post
+            return Err(ThoriumError::Parse(format!("no post in {}", url)));
+        }
+        Ok(post.clone())
     }
 }
 
-pub fn is_bluesky_url(url: &str) -> bool {
-    UrlType::from_string(url) == UrlType::Bluesky
+#[async_trait]
+impl Provider for Bluesky {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// `/profile/<handle>/post/<rkey>`
+    fn is_post(&self, url: &Url) -> bool {
+        matches!(path_segments(url)[..], ["profile", _, "post", _, ..])
+    }
+
+    /// Media comes from the AppView, not from a mirror
+    fn media_mirror(&self) -> Option<&Mirror> {
+        None
+    }
+
+    /// Everything comes from the AppView, even for links on a mirror
+    fn fetched_mirror(&self, _url: &Url) -> Option<&Mirror> {
+        None
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let post = self.fetch_post(client, url).await?;
+
+        let mut items = media_from_embed(&post["embed"]);
+        // Quotes without media of their own show the media of the quoted post
+        if items.is_empty() {
+            if let Some(quoted) = quoted_record(&post["embed"]) {
+                items = media_from_embed(&quoted["embeds"][0]);
+            }
+        }
+        Ok(MediaBundle::new(items).non_empty())
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let post = post_info(&self.fetch_post(client, url).await?);
+        Ok((!post.is_empty()).then_some(post))
+    }
 }
 
-pub async fn convert_url(url: String, from: UrlType, to: UrlType) -> String {
-    url.replace(from.as_str(), to.as_str())
+/// Images or the video of an embed view, the media of posts quoting with media included
+fn media_from_embed(embed: &Value) -> Vec<MediaItem> {
+    match embed["$type"].as_str() {
+        Some("app.bsky.embed.images#view") => embed["images"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|image| {
+                let mut item = MediaItem::new(image["fullsize"].as_str()?, MediaKind::Image);
+                item.thumbnail = image["thumb"].as_str().map(str::to_string);
+                media_details(&mut item, image);
+                Some(item)
+            })
+            .collect(),
+        Some("app.bsky.embed.video#view") => {
+            // Videos are only served as HLS playlists
+            let mut item = match embed["playlist"].as_str() {
+                Some(playlist) => MediaItem::new(playlist, MediaKind::Video),
+                None => return Vec::new(),
+            };
+            item.mime_type = Some("application/vnd.apple.mpegurl".to_string());
+            item.thumbnail = embed["thumbnail"].as_str().map(str::to_string);
+            media_details(&mut item, embed);
+            vec![item]
+        }
+        Some("app.bsky.embed.recordWithMedia#view") => media_from_embed(&embed["media"]),
+        _ => Vec::new(),
+    }
 }
 
-pub async fn convert_url_lazy(url: String, to: UrlType) -> String {
-    let from = UrlType::from_string(&url);
-    url.replace(from.as_str(), to.as_str())
+fn media_details(item: &mut MediaItem, view: &Value) {
+    let dimension = |value: &Value| value.as_u64().and_then(|value| u32::try_from(value).ok());
+    item.width = dimension(&view["aspectRatio"]["width"]);
+    item.height = dimension(&view["aspectRatio"]["height"]);
+    item.alt_text = view["alt"]
+        .as_str()
+        .filter(|alt| !alt.is_empty())
+        .map(str::to_string);
 }
 
-pub async fn get_url_type(url: String) -> UrlType {
-    UrlType::from_string(&url)
+/// The post quoted by the embed, unless it is deleted, blocked or not a post
+fn quoted_record(embed: &Value) -> Option<&Value> {
+    let record = match embed["$type"].as_str()? {
+        "app.bsky.embed.record#view" => &embed["record"],
+        "app.bsky.embed.recordWithMedia#view" => &embed["record"]["record"],
+        _ => return None,
+    };
+    (record["$type"].as_str() == Some("app.bsky.embed.record#viewRecord")).then_some(record)
 }
 
-pub async fn get_media_from_url(mut url: String) -> String {
-    url = convert_url_lazy(url, UrlType::FixBluesky).await;
-    crate::get_media(url).await
+/// Post of a thread, or a quoted post, which keeps its record in `value` and its embeds in a list
+fn post_info(view: &Value) -> PostInfo {
+    let string = |value: &Value| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let record = match &view["value"] {
+        Value::Object(_) => &view["value"],
+        _ => &view["record"],
+    };
+    let embed = match &view["embed"] {
+        Value::Object(_) => &view["embed"],
+        _ => &view["embeds"][0],
+    };
+
+    PostInfo {
+        author_name: string(&view["author"]["displayName"]),
+        author_handle: string(&view["author"]["handle"]),
+        text: string(&record["text"]),
+        created_at: record["createdAt"].as_str().and_then(parse_time),
+        replies: view["replyCount"].as_u64(),
+        reposts: view["repostCount"].as_u64(),
+        likes: view["likeCount"].as_u64(),
+        quoted: quoted_record(embed).map(|quoted| Box::new(post_info(quoted))),
+        language: string(&record["langs"][0]),
+    }
 }
@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{MediaBundle, Result, ThoriumError};
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Most lookups that are kept, the least recently used one is dropped first
+    pub capacity: usize,
+    /// How long found media is kept
+    pub ttl: Duration,
+    /// How long posts without media are remembered
+    pub negative_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            ttl: Duration::from_secs(60 * 60),
+            negative_ttl: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Outcome of a lookup that is worth remembering, errors of the mirrors are not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum CachedMedia {
+    Found(MediaBundle),
+    Nothing,
+    NoMedia,
+}
+
+impl CachedMedia {
+    fn from_result(media: &Result<Option<MediaBundle>>) -> Option<Self> {
+        match media {
+            Ok(Some(bundle)) => Some(CachedMedia::Found(bundle.clone())),
+            Ok(None) => Some(CachedMedia::Nothing),
+            Err(ThoriumError::NoMedia) => Some(CachedMedia::NoMedia),
+            Err(_) => None,
+        }
+    }
+
+    fn into_result(self) -> Result<Option<MediaBundle>> {
+        match self {
+            CachedMedia::Found(bundle) => Ok(Some(bundle)),
+            CachedMedia::Nothing => Ok(None),
+            CachedMedia::NoMedia => Err(ThoriumError::NoMedia),
+        }
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: SystemTime,
+    last_used: u64,
+}
+
+struct Entries<V> {
+    map: HashMap<String, Entry<V>>,
+    /// Incremented on every use, orders the entries by recency
+    clock: u64,
+}
+
+/// In-memory map whose entries expire, dropping the least recently used one when it is full
+pub struct BoundedMap<V> {
+    capacity: usize,
+    entries: Mutex<Entries<V>>,
+}
+
+impl<V: Clone> BoundedMap<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value if it hasn't expired yet
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        match entries.map.get_mut(key) {
+            Some(entry) if entry.expires_at > SystemTime::now() => {
+                entry.last_used = clock;
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                entries.map.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Keep the value for the ttl
+    pub fn insert(&self, key: &str, value: V, ttl: Duration) {
+        self.insert_until(key, value, SystemTime::now() + ttl);
+    }
+
+    fn insert_until(&self, key: &str, value: V, expires_at: SystemTime) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let entry = Entry {
+            value,
+            expires_at,
+            last_used: entries.clock,
+        };
+        entries.map.insert(key.to_string(), entry);
+
+        while entries.map.len() > self.capacity {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.map.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+}
+
+/// TTL and LRU bound cache of media lookups, optionally backed by SQLite so it survives restarts
+pub struct MediaCache {
+    config: CacheConfig,
+    entries: BoundedMap<CachedMedia>,
+    database: Option<Mutex<Connection>>,
+}
+
+impl MediaCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            entries: BoundedMap::new(config.capacity),
+            config,
+            database: None,
+        }
+    }
+
+    /// Cache that also stores its entries in the SQLite database at the path
+    pub fn with_database(config: CacheConfig, path: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "create table if not exists media_cache (
+                key text primary key,
+                media text not null,
+                expires_at integer not null
+            )",
+            [],
+        )?;
+
+        Ok(Self {
+            database: Some(Mutex::new(conn)),
+            ..Self::new(config)
+        })
+    }
+
+    /// Key of a post, post ids are only unique per provider
+    pub fn key(provider: &str, post_id: &str) -> String {
+        format!("{}:{}", provider, post_id)
+    }
+
+    /// Key of a post looked up through a mirror, every mirror describes it its own way, e.g. with its own mosaic
+    pub fn mirror_key(provider: &str, mirror: &str, post_id: &str) -> String {
+        format!("{}@{}:{}", provider, mirror, post_id)
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Lookups held in memory
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remembered outcome of the lookup, None if it has to be done again
+    pub fn get(&self, key: &str) -> Option<Result<Option<MediaBundle>>> {
+        if let Some(media) = self.entries.get(key) {
+            return Some(media.into_result());
+        }
+
+        let (media, expires_at) = self.load(key, SystemTime::now())?;
+        self.entries.insert_until(key, media.clone(), expires_at);
+        Some(media.into_result())
+    }
+
+    /// Remember the outcome of a lookup, errors other than [`ThoriumError::NoMedia`] are skipped
+    pub fn insert(&self, key: &str, media: &Result<Option<MediaBundle>>) {
+        let media = match CachedMedia::from_result(media) {
+            Some(media) => media,
+            None => return,
+        };
+        let ttl = match media {
+            CachedMedia::Found(_) => self.config.ttl,
+            _ => self.config.negative_ttl,
+        };
+        let expires_at = SystemTime::now() + ttl;
+
+        self.store(key, &media, expires_at);
+        self.entries.insert_until(key, media, expires_at);
+    }
+
+    fn load(&self, key: &str, now: SystemTime) -> Option<(CachedMedia, SystemTime)> {
+        let conn = self.database.as_ref()?.lock().unwrap();
+        let row: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT media, expires_at FROM media_cache WHERE key = ?1 AND expires_at > ?2",
+                rusqlite::params![key, unix_seconds(now)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .unwrap_or_else(|why| {
+                println!("Error reading the media cache: {}", why);
+                None
+            });
+
+        let (media, expires_at) = row?;
+        let media = serde_json::from_str(&media).ok()?;
+        Some((media, UNIX_EPOCH + Duration::from_secs(expires_at as u64)))
+    }
+
+    fn store(&self, key: &str, media: &CachedMedia, expires_at: SystemTime) {
+        let conn = match &self.database {
+            Some(conn) => conn.lock().unwrap(),
+            None => return,
+        };
+        let media = match serde_json::to_string(media) {
+            Ok(media) => media,
+            Err(_) => return,
+        };
+
+        // Drop expired entries and keep the table as bounded as the memory
+        let result = conn
+            .execute(
+                "INSERT OR REPLACE INTO media_cache (key, media, expires_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, media, unix_seconds(expires_at)],
+            )
+            .and_then(|_| {
+                conn.execute(
+                    "DELETE FROM media_cache WHERE expires_at <= ?1 OR key NOT IN
+                        (SELECT key FROM media_cache ORDER BY expires_at DESC LIMIT ?2)",
+                    rusqlite::params![unix_seconds(SystemTime::now()), self.config.capacity as i64],
+                )
+            });
+        if let Err(why) = result {
+            println!("Error writing the media cache: {}", why);
+        }
+    }
+}
+
+impl Default for MediaCache {
+    fn default() -> Self {
+        Self::new(CacheConfig::default())
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
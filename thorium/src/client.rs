@@ -0,0 +1,140 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, StatusCode};
+
+use crate::{
+    fetcher::{BodySink, FetchResponse, Fetcher, ReqwestFetcher},
+    Result, ThoriumError,
+};
+
+/// Mirrors serve their embed metadata to Discord's crawler, so we pretend to be it
+pub const USER_AGENT: &str = "Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com)";
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub user_agent: String,
+    pub connect_timeout: Duration,
+    /// Time allowed for the whole request, including reading the body
+    pub request_timeout: Duration,
+    /// Longest wait for the headers or the next piece of the body, so stalled servers fail early
+    pub read_timeout: Duration,
+    /// How often a request is retried after a 5xx or 429 status or a network failure
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every following one
+    pub retry_backoff: Duration,
+    /// How many redirects are followed, 0 disables following them
+    pub max_redirects: usize,
+    /// Largest body [`ThoriumClient::get`] reads, media is downloaded with its own limits
+    pub max_body_bytes: u64,
+    /// Hosts allowed to resolve to loopback or private addresses, such as a self-hosted mirror
+    pub trusted_hosts: Vec<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: USER_AGENT.to_string(),
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(15),
+            read_timeout: Duration::from_secs(10),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            max_redirects: 10,
+            max_body_bytes: 16 * 1024 * 1024,
+            trusted_hosts: Vec::new(),
+        }
+    }
+}
+
+/// HTTP client shared by every provider.
+/// It adds retries and status checks on top of a [`Fetcher`], by default one holding a single connection pool.
+#[derive(Clone)]
+pub struct ThoriumClient {
+    fetcher: Arc<dyn Fetcher>,
+    config: ClientConfig,
+}
+
+impl ThoriumClient {
+    pub fn new(config: ClientConfig) -> Result<Self> {
+        let fetcher = ReqwestFetcher::new(&config)?;
+        Ok(Self::with_fetcher(config, fetcher))
+    }
+
+    /// Client fetching through something else than the network, e.g. a [`ReplayFetcher`](crate::fetcher::ReplayFetcher)
+    pub fn with_fetcher(config: ClientConfig, fetcher: impl Fetcher + 'static) -> Self {
+        Self {
+            fetcher: Arc::new(fetcher),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Request the url, retrying transient failures, and fail on anything but a successful status code
+    pub async fn get(&self, url: &str) -> Result<FetchResponse> {
+        let mut attempt = 0;
+        loop {
+            let (error, retry_after) = match self.fetcher.fetch(url).await {
+                Ok(response) if response.status.is_success() => return Ok(response),
+                Ok(response) => {
+                    let retry_after = response
+                        .headers
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .map(Duration::from_secs);
+                    (ThoriumError::Status(response.status), retry_after)
+                }
+                Err(why) => (why, None),
+            };
+
+            if attempt >= self.config.max_retries || !is_transient(&error) {
+                return Err(error);
+            }
+
+            let backoff = self.config.retry_backoff * 2u32.pow(attempt);
+            tokio::time::sleep(
+                retry_after
+                    .unwrap_or(backoff)
+                    .min(self.config.request_timeout),
+            )
+            .await;
+            attempt += 1;
+        }
+    }
+
+    /// Request the url and hand its body to the sink.
+    /// Not retried, as the sink may already have received part of the body.
+    pub async fn get_into(&self, url: &str, sink: &mut dyn BodySink) -> Result<FetchResponse> {
+        let response = self.fetcher.fetch_into(url, sink).await?;
+        if !response.status.is_success() {
+            return Err(ThoriumError::Status(response.status));
+        }
+        Ok(response)
+    }
+
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        Ok(self.get(url).await?.text())
+    }
+}
+
+impl Default for ThoriumClient {
+    fn default() -> Self {
+        // Building only fails if the TLS backend can't be initialized
+        Self::new(ClientConfig::default()).expect("failed to build the HTTP client")
+    }
+}
+
+/// Whether retrying the request could succeed
+fn is_transient(error: &ThoriumError) -> bool {
+    match error {
+        ThoriumError::Status(status) => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        ThoriumError::Network(why) => why.is_timeout() || why.is_connect(),
+        ThoriumError::Timeout => true,
+        _ => false,
+    }
+}
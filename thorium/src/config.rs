@@ -0,0 +1,180 @@
+use std::{collections::BTreeMap, env, fmt, fs, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{links, ClientConfig, Result, ThoriumError};
+
+/// Environment variable holding the path of the config file
+pub const CONFIG_PATH_VAR: &str = "THORIUM_CONFIG";
+/// Config file read when [`CONFIG_PATH_VAR`] isn't set, it is optional
+pub const DEFAULT_CONFIG_PATH: &str = "thorium.toml";
+/// Prefix of environment variables overriding a single url, e.g. `THORIUM_URL_DDINSTAGRAM`
+pub const URL_VAR_PREFIX: &str = "THORIUM_URL_";
+/// Prefix of environment variables overriding a client setting, e.g. `THORIUM_CLIENT_MAX_RETRIES`
+pub const CLIENT_VAR_PREFIX: &str = "THORIUM_CLIENT_";
+
+/// Settings operators can change without recompiling
+///
+/// ```toml
+/// [urls]
+/// ddinstagram = "https://instagram.example.com/"
+/// vxtwitter_api = "http://localhost:8080/"
+///
+/// [client]
+/// request_timeout_secs = 30
+/// max_retries = 3
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ThoriumConfig {
+    /// Base urls of mirrors and APIs by their id, replacing the built-in defaults
+    pub urls: BTreeMap<String, String>,
+    pub client: ClientSettings,
+}
+
+/// Settings of the HTTP client, the ones left out keep the defaults of [`ClientConfig`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientSettings {
+    pub user_agent: Option<String>,
+    pub connect_timeout_secs: Option<u64>,
+    pub request_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    pub max_redirects: Option<usize>,
+    pub max_body_bytes: Option<u64>,
+}
+
+impl ClientSettings {
+    /// Apply a setting from the environment, `key` being its name in the config file
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "user_agent" => self.user_agent = Some(value.to_string()),
+            "connect_timeout_secs" => self.connect_timeout_secs = Some(number(key, value)?),
+            "request_timeout_secs" => self.request_timeout_secs = Some(number(key, value)?),
+            "read_timeout_secs" => self.read_timeout_secs = Some(number(key, value)?),
+            "max_retries" => self.max_retries = Some(number(key, value)?),
+            "retry_backoff_ms" => self.retry_backoff_ms = Some(number(key, value)?),
+            "max_redirects" => self.max_redirects = Some(number(key, value)?),
+            "max_body_bytes" => self.max_body_bytes = Some(number(key, value)?),
+            _ => {
+                return Err(ThoriumError::Parse(format!(
+                    "unknown client setting {}",
+                    key
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ThoriumConfig {
+    /// Read the config file, then apply the overrides from the environment
+    pub fn load() -> Result<Self> {
+        let mut config = match env::var(CONFIG_PATH_VAR) {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if fs::metadata(DEFAULT_CONFIG_PATH).is_ok() => {
+                Self::from_file(DEFAULT_CONFIG_PATH)?
+            }
+            Err(_) => Self::default(),
+        };
+
+        for (key, value) in env::vars() {
+            if let Some(id) = key.strip_prefix(URL_VAR_PREFIX) {
+                config.urls.insert(id.to_lowercase(), value);
+            } else if let Some(setting) = key.strip_prefix(CLIENT_VAR_PREFIX) {
+                config.client.set(&setting.to_lowercase(), &value)?;
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|why| ThoriumError::Parse(format!("could not read {}: {}", path, why)))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: Self =
+            toml::from_str(content).map_err(|why| ThoriumError::Parse(why.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Override the base url of a mirror or API
+    pub fn with_url(mut self, id: &str, url: &str) -> Self {
+        self.urls.insert(id.to_string(), url.to_string());
+        self
+    }
+
+    /// Settings of the HTTP client, with the defaults for the ones that aren't configured.
+    /// The hosts of the configured urls are trusted, so mirrors can be self-hosted on the local network.
+    pub fn client_config(&self) -> ClientConfig {
+        let defaults = ClientConfig::default();
+        let settings = &self.client;
+        ClientConfig {
+            user_agent: settings.user_agent.clone().unwrap_or(defaults.user_agent),
+            connect_timeout: settings
+                .connect_timeout_secs
+                .map_or(defaults.connect_timeout, Duration::from_secs),
+            request_timeout: settings
+                .request_timeout_secs
+                .map_or(defaults.request_timeout, Duration::from_secs),
+            read_timeout: settings
+                .read_timeout_secs
+                .map_or(defaults.read_timeout, Duration::from_secs),
+            max_retries: settings.max_retries.unwrap_or(defaults.max_retries),
+            retry_backoff: settings
+                .retry_backoff_ms
+                .map_or(defaults.retry_backoff, Duration::from_millis),
+            max_redirects: settings.max_redirects.unwrap_or(defaults.max_redirects),
+            max_body_bytes: settings.max_body_bytes.unwrap_or(defaults.max_body_bytes),
+            trusted_hosts: self
+                .urls
+                .keys()
+                .filter_map(|id| self.configured_url(id))
+                .filter_map(|url| url.host_str().map(str::to_string))
+                .collect(),
+        }
+    }
+
+    /// Base url configured for the id, or the default
+    pub fn url(&self, id: &str, default: &str) -> Url {
+        self.configured_url(id)
+            .or_else(|| links::parse(default))
+            .expect("default urls are valid")
+    }
+
+    /// Base url configured for the id, for mirrors that have no public default
+    pub fn configured_url(&self, id: &str) -> Option<Url> {
+        self.urls.get(id).and_then(|url| links::parse(url))
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self
+            .urls
+            .iter()
+            .find(|(_, url)| links::parse(url).is_none())
+        {
+            Some((id, url)) => Err(ThoriumError::Parse(format!(
+                "invalid url for {}: {}",
+                id, url
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn number<T: FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|why| ThoriumError::Parse(format!("invalid {}: {}", key, why)))
+}
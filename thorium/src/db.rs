@@ -11,9 +11,42 @@ pub struct Server {
     pub bluesky: bool,
     pub instagram: bool,
     pub tiktok: bool,
+    pub reddit: bool,
+    pub fediverse: bool,
+    pub threads: bool,
+    pub pixiv: bool,
+    pub tumblr: bool,
+    pub twitch: bool,
+    pub youtube: bool,
+    /// Pages no other provider supports, through their oEmbed or OpenGraph tags
+    pub generic: bool,
     pub language: Option<String>,
 }
 
+impl Server {
+    /// Whether the provider with the given id is enabled for this server
+    pub fn is_enabled(&self, provider: &str) -> bool {
+        match provider {
+            crate::twitter::ID => self.twitter,
+            crate::bluesky::ID => self.bluesky,
+            crate::instagram::ID => self.instagram,
+            crate::tiktok::ID => self.tiktok,
+            crate::reddit::ID => self.reddit,
+            crate::fediverse::ID => self.fediverse,
+            crate::threads::ID => self.threads,
+            crate::pixiv::ID => self.pixiv,
+            crate::tumblr::ID => self.tumblr,
+            crate::twitch::ID => self.twitch,
+            crate::youtube::ID => self.youtube,
+            crate::generic::ID => self.generic,
+            _ => false,
+        }
+    }
+}
+
+/// SQLite database of the bot, also holds the persisted media cache
+pub const DB_PATH: &str = "sphene.db";
+
 const STANDARD_LANG: &str = "en";
 
 const STANDARD_SERVER: Server = Server {
@@ -22,12 +55,20 @@ const STANDARD_SERVER: Server = Server {
     bluesky: false,
     instagram: true,
     tiktok: false,
+    reddit: false,
+    fediverse: false,
+    threads: false,
+    pixiv: false,
+    tumblr: false,
+    twitch: false,
+    youtube: false,
+    generic: false,
     language: None,
 };
 
 impl DBConn {
     pub fn new() -> Result<Self> {
-        let conn = Connection::open("sphene.db")?;
+        let conn = Connection::open(DB_PATH)?;
         Ok(Self { conn })
     }
 
@@ -40,17 +81,67 @@ impl DBConn {
                     bluesky boolean not null,
                     instagram boolean not null,
                     tiktok boolean not null,
-                    language text
+                    language text,
+                    reddit boolean not null default false,
+                    fediverse boolean not null default false,
+                    threads boolean not null default false,
+                    pixiv boolean not null default false,
+                    tumblr boolean not null default false,
+                    twitch boolean not null default false,
+                    youtube boolean not null default false,
+                    generic boolean not null default false
+                )",
+                [],
+            )
+            .unwrap();
+
+        self.add_column("reddit", "boolean not null default false");
+        self.add_column("fediverse", "boolean not null default false");
+        self.add_column("threads", "boolean not null default false");
+        self.add_column("pixiv", "boolean not null default false");
+        self.add_column("tumblr", "boolean not null default false");
+        self.add_column("twitch", "boolean not null default false");
+        self.add_column("youtube", "boolean not null default false");
+        self.add_column("generic", "boolean not null default false");
+
+        self.conn
+            .execute(
+                "create table if not exists fediverse_instance (
+                    server_id integer not null,
+                    host text not null,
+                    allowed boolean not null,
+                    primary key (server_id, host)
                 )",
                 [],
             )
             .unwrap();
     }
 
+    /// Add a column that databases created by older versions lack
+    fn add_column(&self, name: &str, definition: &str) {
+        let exists = self
+            .conn
+            .prepare("SELECT 1 FROM pragma_table_info('server') WHERE name = ?1")
+            .and_then(|mut stmt| stmt.exists([name]))
+            .unwrap();
+        if !exists {
+            self.conn
+                .execute(
+                    &format!("ALTER TABLE server ADD COLUMN {} {}", name, definition),
+                    [],
+                )
+                .unwrap();
+        }
+    }
+
     pub fn get_server(&self, id: u64, init: bool) -> Server {
         let mut stmt = self
             .conn
-            .prepare("SELECT * FROM server WHERE id = ?1")
+            .prepare(
+                "SELECT id, twitter, bluesky, instagram, tiktok, language, reddit, fediverse, threads, pixiv, tumblr,
+                    twitch, youtube, generic
+                 FROM server WHERE id = ?1",
+            )
             .unwrap();
         let mut server_iter = stmt
             .query_map([id], |row| {
@@ -62,6 +153,14 @@ impl DBConn {
                     bluesky: row.get(2)?,
                     instagram: row.get(3)?,
                     tiktok: row.get(4)?,
+                    reddit: row.get(6)?,
+                    fediverse: row.get(7)?,
+                    threads: row.get(8)?,
+                    pixiv: row.get(9)?,
+                    tumblr: row.get(10)?,
+                    twitch: row.get(11)?,
+                    youtube: row.get(12)?,
+                    generic: row.get(13)?,
                     language: Some(lang),
                 })
             })
@@ -73,8 +172,9 @@ impl DBConn {
             if init {
                 let mut insert_statement = self.conn.prepare(
                     
-                    "INSERT INTO server (id, twitter, bluesky, instagram, tiktok, language)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)").unwrap();
+                    "INSERT INTO server (id, twitter, bluesky, instagram, tiktok, reddit, fediverse, threads, pixiv, tumblr,
+                        twitch, youtube, generic, language)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)").unwrap();
                 insert_statement
                     .execute(rusqlite::params![
                         id,
@@ -82,6 +182,14 @@ impl DBConn {
                         STANDARD_SERVER.bluesky,
                         STANDARD_SERVER.instagram,
                         STANDARD_SERVER.tiktok,
+                        STANDARD_SERVER.reddit,
+                        STANDARD_SERVER.fediverse,
+                        STANDARD_SERVER.threads,
+                        STANDARD_SERVER.pixiv,
+                        STANDARD_SERVER.tumblr,
+                        STANDARD_SERVER.twitch,
+                        STANDARD_SERVER.youtube,
+                        STANDARD_SERVER.generic,
                         STANDARD_LANG.to_string()
                     ])
                     .unwrap();
@@ -111,8 +219,16 @@ impl DBConn {
                         bluesky = ?2, 
                         instagram = ?3, 
                         tiktok = ?4, 
-                        language = ?5 
-                    WHERE id = ?6",
+                        reddit = ?5,
+                        fediverse = ?6,
+                        threads = ?7,
+                        pixiv = ?8,
+                        tumblr = ?9,
+                        twitch = ?10,
+                        youtube = ?11,
+                        generic = ?12,
+                        language = ?13 
+                    WHERE id = ?14",
             )
             .unwrap();
         stmt.execute(rusqlite::params![
@@ -120,9 +236,56 @@ impl DBConn {
             server.bluesky,
             server.instagram,
             server.tiktok,
+            server.reddit,
+            server.fediverse,
+            server.threads,
+            server.pixiv,
+            server.tumblr,
+            server.twitch,
+            server.youtube,
+            server.generic,
             server.language.unwrap_or(STANDARD_LANG.to_string()),
             server.id
         ])
         .unwrap();
     }
-}
\ No newline at end of file
+
+    /// Allow or deny posts from a Fediverse instance on the server
+    pub fn set_instance(&self, server_id: u64, host: &str, allowed: bool) {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO fediverse_instance (server_id, host, allowed)
+                 VALUES (?1, ?2, ?3)",
+                rusqlite::params![server_id, host.to_lowercase(), allowed],
+            )
+            .unwrap();
+    }
+
+    pub fn remove_instance(&self, server_id: u64, host: &str) {
+        self.conn
+            .execute(
+                "DELETE FROM fediverse_instance WHERE server_id = ?1 AND host = ?2",
+                rusqlite::params![server_id, host.to_lowercase()],
+            )
+            .unwrap();
+    }
+
+    /// Denied instances never are, once any instance is allowed only those are
+    pub fn is_instance_allowed(&self, server_id: u64, host: &str) -> bool {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT host, allowed FROM fediverse_instance WHERE server_id = ?1")
+            .unwrap();
+        let instances: Vec<(String, bool)> = stmt
+            .query_map([server_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .filter_map(|instance| instance.ok())
+            .collect();
+
+        let host = host.to_lowercase();
+        match instances.iter().find(|(instance, _)| *instance == host) {
+            Some((_, allowed)) => *allowed,
+            None => !instances.iter().any(|(_, allowed)| *allowed),
+        }
+    }
+}
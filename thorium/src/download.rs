@@ -0,0 +1,327 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use reqwest::header::{HeaderMap, CONTENT_LENGTH};
+
+use crate::fetcher::BodySink;
+use crate::{MediaItem, MediaKind, Result, ThoriumClient, ThoriumError};
+
+/// Largest file Discord accepts from bots on servers without boosts
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Bytes kept from the start of the download to recognize its format
+const SNIFF_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadLimits {
+    /// Downloads are stopped as soon as they grow past this size
+    pub max_bytes: u64,
+}
+
+impl DownloadLimits {
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes }
+    }
+}
+
+impl Default for DownloadLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BYTES)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadContent {
+    Memory(Vec<u8>),
+    /// Path of the file, with the extension of its real format
+    File(PathBuf),
+}
+
+/// Media that was downloaded completely
+#[derive(Debug, Clone, PartialEq)]
+pub struct Download {
+    pub content: DownloadContent,
+    /// Size in bytes
+    pub size: u64,
+    /// Type of the content, recognized from its first bytes when possible
+    pub mime_type: String,
+    /// File extension matching `mime_type`, without the dot
+    pub extension: String,
+    pub kind: MediaKind,
+    /// Name to upload the media as, taken from the link
+    pub file_name: String,
+}
+
+impl Download {
+    /// The downloaded bytes, None if they were written to a file
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.content {
+            DownloadContent::Memory(data) => Some(data),
+            DownloadContent::File(_) => None,
+        }
+    }
+
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self.content {
+            DownloadContent::Memory(data) => Some(data),
+            DownloadContent::File(_) => None,
+        }
+    }
+}
+
+/// Writes the body to `writer` while enforcing the limits
+struct LimitedSink<W> {
+    writer: W,
+    limit: u64,
+    received: u64,
+    /// Size announced by the server
+    expected: Option<u64>,
+    head: Vec<u8>,
+}
+
+impl<W: Write + Send> LimitedSink<W> {
+    fn new(writer: W, limits: &DownloadLimits) -> Self {
+        Self {
+            writer,
+            limit: limits.max_bytes,
+            received: 0,
+            expected: None,
+            head: Vec::with_capacity(SNIFF_LENGTH),
+        }
+    }
+}
+
+impl<W: Write + Send> BodySink for LimitedSink<W> {
+    /// Media announced as too large isn't downloaded at all
+    fn begin(&mut self, headers: &HeaderMap) -> Result<()> {
+        self.expected = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok());
+        match self.expected {
+            Some(size) if size > self.limit => Err(ThoriumError::TooLarge {
+                limit: self.limit,
+                size: Some(size),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        let received = self.received + chunk.len() as u64;
+        if received > self.limit {
+            return Err(ThoriumError::TooLarge {
+                limit: self.limit,
+                size: self.expected,
+            });
+        }
+
+        let missing = SNIFF_LENGTH.saturating_sub(self.head.len());
+        self.head
+            .extend_from_slice(&chunk[..missing.min(chunk.len())]);
+        self.writer.write_all(chunk)?;
+        self.received = received;
+        Ok(())
+    }
+}
+
+/// Download the media into memory
+pub async fn download_media(
+    client: &ThoriumClient,
+    item: &MediaItem,
+    limits: &DownloadLimits,
+) -> Result<Download> {
+    let mut sink = LimitedSink::new(Vec::new(), limits);
+    let response = stream(client, item, &mut sink).await?;
+
+    Ok(Download {
+        content: DownloadContent::Memory(std::mem::take(&mut sink.writer)),
+        ..describe(item, &sink, &response.headers)
+    })
+}
+
+/// Download the media to `path`, whose extension is replaced by the one of the real format.
+/// Nothing is left behind when the download fails.
+pub async fn download_media_to(
+    client: &ThoriumClient,
+    item: &MediaItem,
+    limits: &DownloadLimits,
+    path: impl AsRef<Path>,
+) -> Result<Download> {
+    let part = path.as_ref().with_extension("part");
+    let result = async {
+        let mut sink = LimitedSink::new(BufWriter::new(File::create(&part)?), limits);
+        let response = stream(client, item, &mut sink).await?;
+        sink.writer.flush()?;
+
+        let download = describe(item, &sink, &response.headers);
+        let file = path.as_ref().with_extension(&download.extension);
+        drop(sink);
+        fs::rename(&part, &file)?;
+        Ok(Download {
+            content: DownloadContent::File(file),
+            ..download
+        })
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+/// Request the media into the sink, telling downloads that were cut off apart from other failures
+async fn stream<W: Write + Send>(
+    client: &ThoriumClient,
+    item: &MediaItem,
+    sink: &mut LimitedSink<W>,
+) -> Result<crate::fetcher::FetchResponse> {
+    let response = match client.get_into(&item.url, sink).await {
+        Ok(response) => response,
+        Err(ThoriumError::Network(_) | ThoriumError::Timeout) if sink.received > 0 => {
+            return Err(ThoriumError::Incomplete {
+                received: sink.received,
+                expected: sink.expected,
+            })
+        }
+        Err(why) => return Err(why),
+    };
+
+    match sink.expected {
+        Some(expected) if sink.received < expected => Err(ThoriumError::Incomplete {
+            received: sink.received,
+            expected: Some(expected),
+        }),
+        _ => Ok(response),
+    }
+}
+
+/// Everything about the download but its content
+fn describe<W>(item: &MediaItem, sink: &LimitedSink<W>, headers: &HeaderMap) -> Download {
+    let header = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    // The first bytes are trusted over the server, which is trusted over the link
+    let (mime_type, extension) = match sniff(&sink.head) {
+        Some((mime_type, extension)) => (mime_type.to_string(), extension.to_string()),
+        None => {
+            let from_header = header
+                .filter(|mime| mime != "application/octet-stream")
+                .and_then(|mime| extension_of_mime(&mime).map(|extension| (mime, extension)));
+            match from_header {
+                Some((mime, extension)) => (mime, extension.to_string()),
+                None => {
+                    let extension =
+                        extension_of_url(&item.url).unwrap_or_else(|| "bin".to_string());
+                    let mime = item
+                        .mime_type
+                        .clone()
+                        .unwrap_or_else(|| "application/octet-stream".to_string());
+                    (mime, extension)
+                }
+            }
+        }
+    };
+
+    let kind = match MediaKind::from_mime(&mime_type) {
+        // Gifs are often served as silent videos
+        Some(MediaKind::Video) if item.kind == MediaKind::Gif => MediaKind::Gif,
+        Some(kind) => kind,
+        None => item.kind,
+    };
+
+    Download {
+        content: DownloadContent::Memory(Vec::new()),
+        size: sink.received,
+        file_name: format!("{}.{}", file_stem(&item.url), extension),
+        mime_type,
+        extension,
+        kind,
+    }
+}
+
+/// Recognize the format of media from its first bytes, as its MIME type and file extension
+pub fn sniff(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        Some(("image/jpeg", "jpg"))
+    } else if at(0, b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        Some(("image/webp", "webp"))
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        Some(("audio/wav", "wav"))
+    } else if at(4, b"ftyp") {
+        // The brand tells QuickTime and audio-only files apart from regular mp4
+        match bytes.get(8..12) {
+            Some(b"qt  ") => Some(("video/quicktime", "mov")),
+            Some(b"M4A ") => Some(("audio/mp4", "m4a")),
+            _ => Some(("video/mp4", "mp4")),
+        }
+    } else if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some(("video/webm", "webm"))
+    } else if at(0, b"OggS") {
+        Some(("audio/ogg", "ogg"))
+    } else if at(0, b"ID3") || bytes.len() > 1 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0 {
+        Some(("audio/mpeg", "mp3"))
+    } else if at(0, b"#EXTM3U") {
+        Some(("application/vnd.apple.mpegurl", "m3u8"))
+    } else {
+        None
+    }
+}
+
+fn extension_of_mime(mime: &str) -> Option<&'static str> {
+    Some(match mime {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "video/mp4" => "mp4",
+        "video/quicktime" => "mov",
+        "video/webm" => "webm",
+        "audio/mpeg" => "mp3",
+        "audio/mp4" => "m4a",
+        "audio/ogg" => "ogg",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "application/vnd.apple.mpegurl" | "application/x-mpegurl" => "m3u8",
+        _ => return None,
+    })
+}
+
+fn extension_of_url(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let (_, extension) = path.rsplit_once('/')?.1.rsplit_once('.')?;
+    (!extension.is_empty()
+        && extension.len() <= 4
+        && extension.bytes().all(|byte| byte.is_ascii_alphanumeric()))
+    .then(|| extension.to_ascii_lowercase())
+}
+
+/// Last segment of the link without its extension, safe to use as a file name
+fn file_stem(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let stem = name.split_once('.').map_or(name, |(stem, _)| stem);
+    let stem: String = stem
+        .chars()
+        .filter(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | '_'))
+        .take(64)
+        .collect();
+
+    if stem.is_empty() {
+        "media".to_string()
+    } else {
+        stem
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+pub type Result<T> = std::result::Result<T, ThoriumError>;
+
+#[derive(Debug)]
+pub enum ThoriumError {
+    /// The request could not be sent or its body could not be read
+    Network(reqwest::Error),
+    /// The server stopped answering for longer than the read timeout
+    Timeout,
+    /// The server answered with a non-success status code
+    Status(StatusCode),
+    /// The response could not be understood
+    Parse(String),
+    /// No provider supports the url
+    UnsupportedUrl(String),
+    /// The response announced media but did not contain a usable link to it
+    NoMedia,
+    /// The media is larger than the limit of the download, `size` is known when the server announced it
+    TooLarge { limit: u64, size: Option<u64> },
+    /// The download stopped before the whole media was received
+    Incomplete {
+        received: u64,
+        expected: Option<u64>,
+    },
+    /// The downloaded media could not be written
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ThoriumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThoriumError::Network(why) => write!(f, "network error: {}", why),
+            ThoriumError::Timeout => write!(f, "the server stopped answering"),
+            ThoriumError::Status(status) => write!(f, "unexpected status code: {}", status),
+            ThoriumError::Parse(why) => write!(f, "could not parse response: {}", why),
+            ThoriumError::UnsupportedUrl(url) => write!(f, "unsupported url: {}", url),
+            ThoriumError::NoMedia => write!(f, "no media found"),
+            ThoriumError::TooLarge { limit, size } => match size {
+                Some(size) => write!(
+                    f,
+                    "media of {} bytes exceeds the limit of {} bytes",
+                    size, limit
+                ),
+                None => write!(f, "media exceeds the limit of {} bytes", limit),
+            },
+            ThoriumError::Incomplete { received, expected } => match expected {
+                Some(expected) => write!(
+                    f,
+                    "download stopped after {} of {} bytes",
+                    received, expected
+                ),
+                None => write!(f, "download stopped after {} bytes", received),
+            },
+            ThoriumError::Io(why) => write!(f, "could not write media: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ThoriumError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ThoriumError::Network(why) => Some(why),
+            ThoriumError::Io(why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ThoriumError {
+    fn from(why: reqwest::Error) -> Self {
+        match why.status() {
+            Some(status) => ThoriumError::Status(status),
+            None => ThoriumError::Network(why),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ThoriumError {
+    fn from(why: serde_json::Error) -> Self {
+        ThoriumError::Parse(why.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for ThoriumError {
+    fn from(why: tokio::task::JoinError) -> Self {
+        ThoriumError::Parse(why.to_string())
+    }
+}
+
+impl From<image::ImageError> for ThoriumError {
+    fn from(why: image::ImageError) -> Self {
+        ThoriumError::Parse(why.to_string())
+    }
+}
+
+impl From<std::io::Error> for ThoriumError {
+    fn from(why: std::io::Error) -> Self {
+        ThoriumError::Io(why)
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use url::Url;
+
+use crate::cache::BoundedMap;
+use crate::links::{is_public, path_segments};
+use crate::post::{html_to_text, parse_time};
+use crate::provider::{Mirror, Provider};
+use crate::{MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumError};
+
+pub const ID: &str = "fediverse";
+
+/// Prefix of the `rel` NodeInfo uses to link its schemas
+const NODEINFO_SCHEMA: &str = "http://nodeinfo.diaspora.software/ns/schema/";
+
+/// Most hosts whose NodeInfo is remembered, every host anyone posts a link to ends up here
+const INSTANCE_CAPACITY: usize = 4096;
+/// How long the answer of a host is trusted
+const INSTANCE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Mastodon, Misskey and other ActivityPub servers.
+/// They live on any host, so links are detected by the shape of their path and confirmed through NodeInfo.
+/// Only links on hosts confirmed that way are owned.
+pub struct Fediverse {
+    /// Whether a host is an instance, by host
+    instances: BoundedMap<bool>,
+}
+
+impl Fediverse {
+    pub fn new() -> Self {
+        Self {
+            instances: BoundedMap::new(INSTANCE_CAPACITY),
+        }
+    }
+
+    /// Id of the status if the url has a shape the Mastodon API knows
+    fn status_id<'a>(&self, url: &'a Url) -> Option<&'a str> {
+        match path_segments(url)[..] {
+            [user, id] if user.starts_with('@') => Some(id),
+            ["users", _, "statuses", id] => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Whether the host already confirmed being an instance
+    fn is_known_instance(&self, host: &str) -> bool {
+        self.instances.get(host) == Some(true)
+    }
+
+    /// Ask the host for its NodeInfo, every ActivityPub server worth supporting publishes one
+    async fn is_instance(&self, client: &ThoriumClient, host: &str) -> Result<bool> {
+        if let Some(known) = self.instances.get(host) {
+            return Ok(known);
+        }
+
+        let is_instance = match client
+            .get_text(&format!("https://{}/.well-known/nodeinfo", host))
+            .await
+        {
+            Ok(content) => serde_json::from_str::<Value>(&content)
+                .ok()
+                .and_then(|nodeinfo| nodeinfo["links"].as_array().cloned())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|link| link["rel"].as_str())
+                .any(|rel| rel.starts_with(NODEINFO_SCHEMA)),
+            Err(ThoriumError::Status(_)) => false,
+            Err(why) => return Err(why),
+        };
+
+        self.instances.insert(host, is_instance, INSTANCE_TTL);
+        Ok(is_instance)
+    }
+
+    async fn fetch_status(&self, client: &ThoriumClient, url: &Url, id: &str) -> Result<Value> {
+        let api_url = format!(
+            "https://{}/api/v1/statuses/{}",
+            url.host_str().unwrap_or_default(),
+            id
+        );
+        let status: Value = serde_json::from_str(&client.get_text(&api_url).await?)?;
+
+        // Boosts carry the boosted status
+        match &status["reblog"] {
+            Value::Object(_) => Ok(status["reblog"].clone()),
+            _ => Ok(status),
+        }
+    }
+}
+
+impl Default for Fediverse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Provider for Fediverse {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &[]
+    }
+
+    /// `/@<user>/<id>`, `/users/<user>/statuses/<id>` (Mastodon and compatible) or `/notes/<id>` (Misskey)
+    fn is_post(&self, url: &Url) -> bool {
+        let id = match path_segments(url)[..] {
+            [user, id] if user.len() > 1 && user.starts_with('@') => id,
+            ["users", _, "statuses", id] | ["notes", id] => id,
+            _ => return false,
+        };
+        !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_alphanumeric())
+    }
+
+    /// Ids are only unique per instance
+    fn post_id(&self, url: &Url) -> Option<String> {
+        let id = path_segments(url).last()?.to_string();
+        self.is_post(url)
+            .then(|| format!("{}/{}", url.host_str().unwrap_or_default(), id))
+    }
+
+    fn detect(&self, url: &Url) -> bool {
+        self.is_post(url)
+    }
+
+    /// Paths of this shape exist on other sites too, so the host has to be verified first
+    fn owns(&self, url: &Url) -> bool {
+        self.is_post(url)
+            && url
+                .host_str()
+                .is_some_and(|host| self.is_known_instance(host))
+    }
+
+    /// Posts already are on their instance
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        let _ = url.set_scheme("https");
+        url
+    }
+
+    /// Hosts on the local network are never asked, links come from anyone
+    async fn verify(&self, client: &ThoriumClient, url: &Url) -> bool {
+        match url.host_str() {
+            Some(host) if is_public(url) => self.is_instance(client, host).await.unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let url = self.canonicalize(url);
+        let id = match self.status_id(&url) {
+            Some(id) => id,
+            None => return crate::get_media(client, url.to_string()).await,
+        };
+
+        match self.fetch_status(client, &url, id).await {
+            Ok(status) => {
+                let attachments = status["media_attachments"].as_array().cloned();
+                let items = attachments
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(media_item)
+                    .collect();
+                Ok(MediaBundle::new(items).non_empty())
+            }
+            // Servers that don't speak the Mastodon API or require signed requests still have embeds
+            Err(ThoriumError::Status(_) | ThoriumError::Parse(_)) => {
+                crate::get_media(client, url.to_string()).await
+            }
+            Err(why) => Err(why),
+        }
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let url = self.canonicalize(url);
+        let id = match self.status_id(&url) {
+            Some(id) => id,
+            None => return crate::get_post(client, url.to_string()).await,
+        };
+
+        match self.fetch_status(client, &url, id).await {
+            Ok(status) => {
+                let post = post_info(&status, url.host_str().unwrap_or_default());
+                Ok((!post.is_empty()).then_some(post))
+            }
+            Err(ThoriumError::Status(_) | ThoriumError::Parse(_)) => {
+                crate::get_post(client, url.to_string()).await
+            }
+            Err(why) => Err(why),
+        }
+    }
+}
+
+/// An entry of `media_attachments`
+fn media_item(attachment: &Value) -> Option<MediaItem> {
+    let kind = match attachment["type"].as_str()? {
+        "image" => MediaKind::Image,
+        "video" => MediaKind::Video,
+        "gifv" => MediaKind::Gif,
+        "audio" => MediaKind::Audio,
+        _ => return None,
+    };
+    let url = attachment["url"]
+        .as_str()
+        .or(attachment["remote_url"].as_str())?;
+
+    let original = &attachment["meta"]["original"];
+    let mut item = MediaItem::new(url, kind);
+    item.width = original["width"].as_u64().map(|width| width as u32);
+    item.height = original["height"].as_u64().map(|height| height as u32);
+    item.duration = original["duration"].as_f64();
+    item.thumbnail = attachment["preview_url"].as_str().map(str::to_string);
+    item.alt_text = attachment["description"]
+        .as_str()
+        .filter(|description| !description.is_empty())
+        .map(str::to_string);
+    Some(item)
+}
+
+fn post_info(status: &Value, host: &str) -> PostInfo {
+    let string = |value: &Value| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    // Accounts of the instance itself come without a host
+    let handle = string(&status["account"]["acct"]).map(|acct| {
+        if acct.contains('@') {
+            acct
+        } else {
+            format!("{}@{}", acct, host)
+        }
+    });
+
+    PostInfo {
+        author_name: string(&status["account"]["display_name"]),
+        author_handle: handle,
+        text: string(&status["content"])
+            .map(|content| html_to_text(&content))
+            .filter(|text| !text.is_empty()),
+        created_at: status["created_at"].as_str().and_then(parse_time),
+        replies: status["replies_count"].as_u64(),
+        reposts: status["reblogs_count"].as_u64(),
+        likes: status["favourites_count"].as_u64(),
+        language: string(&status["language"]),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::HashMap,
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    redirect, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{links, ClientConfig, Result, ThoriumError};
+
+/// A response with its whole body already read
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    /// Url the response came from after following redirects
+    pub url: String,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl FetchResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
+/// Receives the body of a response piece by piece, so it doesn't have to be held in memory
+pub trait BodySink: Send {
+    /// Called with the headers before the first chunk, failing skips the body entirely
+    fn begin(&mut self, _headers: &HeaderMap) -> Result<()> {
+        Ok(())
+    }
+
+    /// Failing stops reading the rest of the body
+    fn write(&mut self, chunk: &[u8]) -> Result<()>;
+}
+
+/// The network layer of thorium, swapped out to run against recorded responses
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// Request the url, non-success status codes are not an error at this level
+    async fn fetch(&self, url: &str) -> Result<FetchResponse>;
+
+    /// Like [`fetch`](Self::fetch), but the body of successful responses goes to the sink instead of the response
+    async fn fetch_into(&self, url: &str, sink: &mut dyn BodySink) -> Result<FetchResponse> {
+        let mut response = self.fetch(url).await?;
+        if response.status.is_success() {
+            sink.begin(&response.headers)?;
+            sink.write(&std::mem::take(&mut response.body))?;
+        }
+        Ok(response)
+    }
+}
+
+/// Resolves host names to their public addresses only, so that no link can reach the network the bot runs in,
+/// whatever its name looks like or whichever redirect led to it
+struct PublicResolver {
+    /// Hosts whose private addresses are kept, see [`ClientConfig::trusted_hosts`]
+    trusted_hosts: Vec<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let trusted = self
+            .trusted_hosts
+            .iter()
+            .any(|host| host.eq_ignore_ascii_case(name.as_str()));
+        Box::pin(async move {
+            let addresses: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| trusted || links::is_public_ip(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+/// Fetches over the network
+#[derive(Debug, Clone)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+    read_timeout: Duration,
+    max_body_bytes: u64,
+}
+
+impl ReqwestFetcher {
+    pub fn new(config: &ClientConfig) -> Result<Self> {
+        let redirect_policy = match config.max_redirects {
+            0 => redirect::Policy::none(),
+            max => redirect::Policy::custom(move |attempt| {
+                // Links from messages must not be able to reach the network the bot runs in
+                let from_public = attempt.previous().last().is_some_and(links::is_public);
+                if attempt.previous().len() > max {
+                    attempt.error("too many redirects")
+                } else if from_public && !links::is_public(attempt.url()) {
+                    attempt.error("redirect to a private host")
+                } else {
+                    attempt.follow()
+                }
+            }),
+        };
+
+        let client = reqwest::Client::builder()
+            .user_agent(config.user_agent.as_str())
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .redirect(redirect_policy)
+            .dns_resolver(Arc::new(PublicResolver {
+                trusted_hosts: config.trusted_hosts.clone(),
+            }))
+            .build()?;
+
+        Ok(Self {
+            client,
+            read_timeout: config.read_timeout,
+            max_body_bytes: config.max_body_bytes,
+        })
+    }
+
+    /// Wait for the next step of the response, giving up once the server has been silent for the read timeout
+    async fn read<T>(&self, step: impl Future<Output = reqwest::Result<T>>) -> Result<T> {
+        match tokio::time::timeout(self.read_timeout, step).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(ThoriumError::Timeout),
+        }
+    }
+}
+
+/// Everything but the body of the response
+fn head(response: &reqwest::Response) -> FetchResponse {
+    FetchResponse {
+        url: response.url().to_string(),
+        status: response.status(),
+        headers: response.headers().clone(),
+        body: Vec::new(),
+    }
+}
+
+#[async_trait]
+impl Fetcher for ReqwestFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        let mut response = self.read(self.client.get(url).send()).await?;
+        let mut fetched = head(&response);
+        let too_large = |size| ThoriumError::TooLarge {
+            limit: self.max_body_bytes,
+            size,
+        };
+        if let Some(size) = response
+            .content_length()
+            .filter(|size| *size > self.max_body_bytes)
+        {
+            return Err(too_large(Some(size)));
+        }
+        while let Some(chunk) = self.read(response.chunk()).await? {
+            if (fetched.body.len() + chunk.len()) as u64 > self.max_body_bytes {
+                return Err(too_large(None));
+            }
+            fetched.body.extend_from_slice(&chunk);
+        }
+        Ok(fetched)
+    }
+
+    async fn fetch_into(&self, url: &str, sink: &mut dyn BodySink) -> Result<FetchResponse> {
+        let mut response = self.read(self.client.get(url).send()).await?;
+        let head = head(&response);
+
+        if head.status.is_success() {
+            sink.begin(&head.headers)?;
+            while let Some(chunk) = self.read(response.chunk()).await? {
+                sink.write(&chunk)?;
+            }
+        }
+        Ok(head)
+    }
+}
+
+/// Everything but the body of a recorded response, stored next to it
+#[derive(Serialize, Deserialize)]
+struct FixtureMeta {
+    url: String,
+    status: u16,
+    content_type: Option<String>,
+}
+
+/// File name a response for the url is stored under
+pub fn fixture_name(url: &str) -> String {
+    let url = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+
+    url.chars()
+        .map(|char| match char {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => char,
+            _ => '_',
+        })
+        .collect::<String>()
+        .trim_end_matches('_')
+        .to_string()
+}
+
+/// Paths of the body and the metadata recorded under the name
+fn fixture_paths(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (dir.join(name), dir.join(format!("{}.meta", name)))
+}
+
+/// Serves responses recorded by [`RecordingFetcher`] from a directory.
+/// Urls without a recording are answered with a 404.
+///
+/// The n-th request of a url is answered with the recording `<name>@<n>` when there is one,
+/// so that tests can replay a server that fails before it succeeds.
+#[derive(Debug, Clone)]
+pub struct ReplayFetcher {
+    dir: PathBuf,
+    /// How often every url was requested
+    requests: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl ReplayFetcher {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            requests: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Fetcher for ReplayFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        let request = {
+            let mut requests = self.requests.lock().unwrap();
+            let count = requests.entry(url.to_string()).or_default();
+            *count += 1;
+            *count
+        };
+        let name = fixture_name(url);
+        let (body_path, meta_path) =
+            match fixture_paths(&self.dir, &format!("{}@{}", name, request)) {
+                (body_path, meta_path) if body_path.exists() => (body_path, meta_path),
+                _ => fixture_paths(&self.dir, &name),
+            };
+
+        let body = match fs::read(body_path) {
+            Ok(body) => body,
+            Err(_) => {
+                return Ok(FetchResponse {
+                    url: url.to_string(),
+                    status: StatusCode::NOT_FOUND,
+                    headers: HeaderMap::new(),
+                    body: Vec::new(),
+                })
+            }
+        };
+
+        // Without metadata the recording is a plain successful response
+        let meta = fs::read_to_string(meta_path)
+            .ok()
+            .and_then(|meta| serde_json::from_str::<FixtureMeta>(&meta).ok());
+
+        let mut headers = HeaderMap::new();
+        let content_type = meta.as_ref().and_then(|meta| meta.content_type.as_deref());
+        if let Some(content_type) = content_type.and_then(|value| HeaderValue::from_str(value).ok())
+        {
+            headers.insert(CONTENT_TYPE, content_type);
+        }
+
+        Ok(FetchResponse {
+            url: meta
+                .as_ref()
+                .map_or_else(|| url.to_string(), |meta| meta.url.clone()),
+            status: meta
+                .and_then(|meta| StatusCode::from_u16(meta.status).ok())
+                .unwrap_or(StatusCode::OK),
+            headers,
+            body,
+        })
+    }
+}
+
+/// Passes requests through and stores every response for [`ReplayFetcher`]
+pub struct RecordingFetcher<F: Fetcher> {
+    inner: F,
+    dir: PathBuf,
+}
+
+impl<F: Fetcher> RecordingFetcher<F> {
+    pub fn new(inner: F, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+
+    fn record(&self, url: &str, response: &FetchResponse) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let (body_path, meta_path) = fixture_paths(&self.dir, &fixture_name(url));
+        let meta = FixtureMeta {
+            url: response.url.clone(),
+            status: response.status.as_u16(),
+            content_type: response.content_type().map(str::to_string),
+        };
+
+        fs::write(body_path, &response.body)?;
+        fs::write(meta_path, serde_json::to_string_pretty(&meta)?)
+    }
+}
+
+#[async_trait]
+impl<F: Fetcher> Fetcher for RecordingFetcher<F> {
+    async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        let response = self.inner.fetch(url).await?;
+        if let Err(why) = self.record(url, &response) {
+            println!("Error recording {}: {}", url, why);
+        }
+        Ok(response)
+    }
+}
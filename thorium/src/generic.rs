@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::task::spawn_blocking;
+use url::{Position, Url};
+
+use crate::links::{self, has_host, is_public};
+use crate::post::parse_time;
+use crate::provider::{Mirror, Provider};
+use crate::{
+    scrape_media, MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumError,
+};
+
+pub const ID: &str = "generic";
+
+/// oEmbed endpoints of sites whose pages don't announce them, by host
+static OEMBED_ENDPOINTS: [(&str, &str); 6] = [
+    ("vimeo.com", "https://vimeo.com/api/oembed.json"),
+    ("www.flickr.com", "https://www.flickr.com/services/oembed/"),
+    ("flic.kr", "https://www.flickr.com/services/oembed/"),
+    ("soundcloud.com", "https://soundcloud.com/oembed"),
+    ("open.spotify.com", "https://open.spotify.com/oembed"),
+    ("giphy.com", "https://giphy.com/services/oembed"),
+];
+
+/// Any page no other provider claims, described by its oEmbed endpoint or its OpenGraph and Twitter Card tags.
+/// It matches every link, so it is only used as the fallback of [`ProviderRegistry`](crate::provider::ProviderRegistry).
+#[derive(Default)]
+pub struct Generic;
+
+/// What a page tells about itself
+struct Page {
+    content: String,
+    oembed: Option<Value>,
+    post: PostInfo,
+}
+
+impl Generic {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn fetch_page(&self, client: &ThoriumClient, url: &Url) -> Result<Page> {
+        let content = get_public(client, url).await?;
+
+        // Parsing the page is blocking, so it happens in its own thread
+        let (content, announced, post) = spawn_blocking(move || {
+            let html = scraper::Html::parse_document(&content);
+            let announced = announced_oembed(&html);
+            let post = page_post(&html);
+            (content, announced, post)
+        })
+        .await?;
+
+        let endpoint = match announced.and_then(|href| url.join(&href).ok()) {
+            Some(endpoint) => Some(endpoint),
+            None => registered_oembed(url),
+        };
+        // The tags of the page still describe it when the endpoint fails.
+        // Pages choose the endpoint they announce, so it is held to the same rules as the page.
+        let oembed = match endpoint {
+            Some(endpoint) => get_public(client, &endpoint)
+                .await
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok()),
+            None => None,
+        };
+
+        Ok(Page {
+            content,
+            oembed,
+            post,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for Generic {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &[]
+    }
+
+    /// Any web page on the public internet
+    fn is_post(&self, url: &Url) -> bool {
+        is_public(url)
+    }
+
+    /// Pages are only unique with their host and query
+    fn post_id(&self, url: &Url) -> Option<String> {
+        self.is_post(url)
+            .then(|| url[Position::BeforeHost..Position::AfterQuery].to_string())
+    }
+
+    fn detect(&self, url: &Url) -> bool {
+        self.is_post(url)
+    }
+
+    fn owns(&self, url: &Url) -> bool {
+        self.is_post(url)
+    }
+
+    /// Pages already are on their site, only the fragment is dropped
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        url
+    }
+
+    /// OpenGraph media, then Twitter Card images, then the photo or thumbnail of the oEmbed
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let page = self.fetch_page(client, &self.canonicalize(url)).await?;
+
+        let media = match scrape_media(page.content.clone(), "og:image").await {
+            Ok(None) | Err(ThoriumError::NoMedia) => {
+                scrape_media(page.content, "twitter:image").await
+            }
+            media => media,
+        };
+        let media = match media {
+            Ok(None) | Err(ThoriumError::NoMedia) => page
+                .oembed
+                .as_ref()
+                .and_then(media_from_oembed)
+                .map(|item| MediaBundle::new(vec![item])),
+            media => media?,
+        };
+
+        // The media is downloaded later on, pages can't point it at private hosts either
+        Ok(media
+            .map(|mut bundle| {
+                bundle
+                    .items
+                    .retain(|item| links::parse(&item.url).is_some_and(|url| is_public(&url)));
+                bundle
+            })
+            .filter(|bundle| !bundle.items.is_empty()))
+    }
+
+    /// The oEmbed knows the author better than the tags of the page
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let page = self.fetch_page(client, &self.canonicalize(url)).await?;
+        let post = match &page.oembed {
+            Some(oembed) => post_from_oembed(oembed).merge(page.post),
+            None => page.post,
+        };
+        Ok((!post.is_empty()).then_some(post))
+    }
+}
+
+/// Request the text of a page on the public internet, refusing pages that redirected elsewhere
+async fn get_public(client: &ThoriumClient, url: &Url) -> Result<String> {
+    if !is_public(url) {
+        return Err(ThoriumError::UnsupportedUrl(url.to_string()));
+    }
+
+    let response = client.get(url.as_str()).await?;
+    match links::parse(&response.url) {
+        Some(url) if is_public(&url) => Ok(response.text()),
+        _ => Err(ThoriumError::UnsupportedUrl(response.url)),
+    }
+}
+
+/// Endpoint the page links to with `<link rel="alternate" type="application/json+oembed">`
+fn announced_oembed(html: &scraper::Html) -> Option<String> {
+    let selector = scraper::Selector::parse(
+        "link[rel='alternate'][type='application/json+oembed'][href], \
+         link[rel='alternate'][type='text/json+oembed'][href]",
+    )
+    .unwrap();
+    html.select(&selector)
+        .next()
+        .and_then(|link| link.value().attr("href"))
+        .map(str::to_string)
+}
+
+/// Endpoint of the built-in registry for the site of the page
+fn registered_oembed(url: &Url) -> Option<Url> {
+    let (_, endpoint) = OEMBED_ENDPOINTS
+        .iter()
+        .find(|(host, _)| has_host(url, &[host]))?;
+    Url::parse_with_params(endpoint, &[("url", url.as_str()), ("format", "json")]).ok()
+}
+
+/// Title, description and author from the OpenGraph and Twitter Card tags of the page
+fn page_post(html: &scraper::Html) -> PostInfo {
+    let meta = |property: &str| {
+        let selector = scraper::Selector::parse(&format!(
+            "meta[property='{0}'][content], meta[name='{0}'][content]",
+            property
+        ))
+        .unwrap();
+        html.select(&selector)
+            .next()
+            .and_then(|tag| tag.value().attr("content"))
+            .map(str::trim)
+            .filter(|content| !content.is_empty())
+            .map(str::to_string)
+    };
+    let element = |selector: &str| {
+        let selector = scraper::Selector::parse(selector).unwrap();
+        html.select(&selector).next()
+    };
+
+    let title = meta("og:title")
+        .or_else(|| meta("twitter:title"))
+        .or_else(|| {
+            element("title")
+                .map(|title| title.text().collect::<String>().trim().to_string())
+                .filter(|title| !title.is_empty())
+        });
+    let description = meta("og:description")
+        .or_else(|| meta("twitter:description"))
+        .or_else(|| meta("description"));
+    let text = match (title, description) {
+        (Some(title), Some(description)) => Some(format!("{}\n\n{}", title, description)),
+        (title, description) => title.or(description),
+    };
+
+    PostInfo {
+        author_name: meta("author"),
+        author_handle: meta("twitter:creator")
+            .map(|handle| handle.trim_start_matches('@').to_string()),
+        text,
+        created_at: meta("article:published_time").and_then(|time| parse_time(&time)),
+        language: meta("og:locale").or_else(|| {
+            element("html[lang]")
+                .and_then(|html| html.value().attr("lang"))
+                .map(str::to_string)
+        }),
+        ..Default::default()
+    }
+}
+
+fn post_from_oembed(oembed: &Value) -> PostInfo {
+    let string = |value: &Value| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    PostInfo {
+        author_name: string(&oembed["author_name"]),
+        text: string(&oembed["title"]),
+        ..Default::default()
+    }
+}
+
+/// The image of photos, the thumbnail of videos and rich embeds which only come as HTML players
+fn media_from_oembed(oembed: &Value) -> Option<MediaItem> {
+    let dimension = |value: &Value| value.as_u64().and_then(|value| u32::try_from(value).ok());
+
+    let mut item = match oembed["type"].as_str() {
+        Some("photo") => {
+            let mut item = MediaItem::new(oembed["url"].as_str()?, MediaKind::Image);
+            item.width = dimension(&oembed["width"]);
+            item.height = dimension(&oembed["height"]);
+            item
+        }
+        _ => {
+            let mut item = MediaItem::new(oembed["thumbnail_url"].as_str()?, MediaKind::Image);
+            item.width = dimension(&oembed["thumbnail_width"]);
+            item.height = dimension(&oembed["thumbnail_height"]);
+            item
+        }
+    };
+    item.alt_text = oembed["title"].as_str().map(str::to_string);
+    Some(item)
+}
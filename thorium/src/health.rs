@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{Result, ThoriumError};
+
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    /// Consecutive failures after which a mirror is considered down
+    pub failure_threshold: u32,
+    /// Time between two probes of every mirror
+    pub probe_interval: Duration,
+    /// A mirror that is down gets another chance after this long, even without a successful probe
+    pub retry_after: Duration,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            probe_interval: Duration::from_secs(5 * 60),
+            retry_after: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MirrorState {
+    failures: u32,
+    down_since: Option<Instant>,
+}
+
+/// Health of every mirror by its id, fed by probes and by the outcome of real requests.
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct MirrorHealth {
+    config: HealthConfig,
+    states: Arc<Mutex<HashMap<String, MirrorState>>>,
+}
+
+impl MirrorHealth {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            states: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
+    /// Whether links should be rewritten to the mirror, unknown mirrors are assumed to work
+    pub fn is_healthy(&self, id: &str) -> bool {
+        let states = self.states.lock().unwrap();
+        match states.get(id).and_then(|state| state.down_since) {
+            Some(down_since) => down_since.elapsed() >= self.config.retry_after,
+            None => true,
+        }
+    }
+
+    pub fn record_success(&self, id: &str) {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), MirrorState::default());
+    }
+
+    pub fn record_failure(&self, id: &str) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(id.to_string()).or_default();
+        state.failures += 1;
+
+        // Failing again after getting another chance restarts the wait
+        let retried = state
+            .down_since
+            .is_none_or(|down_since| down_since.elapsed() >= self.config.retry_after);
+        if state.failures >= self.config.failure_threshold && retried {
+            state.down_since = Some(Instant::now());
+        }
+    }
+
+    /// Count the outcome of a request to the mirror
+    pub fn record<T>(&self, id: &str, result: &Result<T>) {
+        match result {
+            Err(why) if is_mirror_failure(why) => self.record_failure(id),
+            _ => self.record_success(id),
+        }
+    }
+}
+
+/// Whether the error means the mirror is down, rather than it answering about a bad post
+pub fn is_mirror_failure(error: &ThoriumError) -> bool {
+    match error {
+        ThoriumError::Network(_) => true,
+        ThoriumError::Status(status) => status.is_server_error(),
+        _ => false,
+    }
+}
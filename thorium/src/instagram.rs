@@ -1,51 +1,129 @@
-pub const INSTAGRAM_URL: &str = "https://www.instagram.com/";
-pub const INSTAGRAM_NWWW_URL: &str = "https://www.instagram.com/";
+use async_trait::async_trait;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::provider::{to_original, Mirror, Provider, ShortLinks};
+use crate::tracking::remove_tracking;
+use crate::{MediaBundle, PostInfo, Result, ThoriumClient, ThoriumConfig};
+
+pub const ID: &str = "instagram";
+
+pub const INSTAGRAM_HOST: &str = "www.instagram.com";
+
+/// Id of the mirror
+pub const DDINSTAGRAM: &str = "ddinstagram";
+
 pub const DDINSTAGRAM_URL: &str = "https://www.ddinstagram.com/";
 
-#[derive(Debug, PartialEq)]
-pub enum UrlType {
-    Instagram,
-    DDInstagram,
-    Unknown
+static HOSTS: [&str; 3] = [INSTAGRAM_HOST, "instagram.com", "m.instagram.com"];
+
+/// Query parameters Instagram adds to shared links
+static TRACKING_PARAMETERS: [&str; 2] = ["igsh", "igshid"];
+
+pub struct Instagram {
+    mirrors: Vec<Mirror>,
+    short_links: ShortLinks,
 }
 
-impl UrlType {
-    pub fn as_str(&self) -> &'static str {
-        match *self {
-            UrlType::Instagram => INSTAGRAM_URL,
-            UrlType::DDInstagram => DDINSTAGRAM_URL,
-            UrlType::Unknown => "",
+impl Instagram {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![Mirror::new(DDINSTAGRAM, DDINSTAGRAM_URL, config)],
+            short_links: ShortLinks::default(),
         }
     }
 
-    pub fn from_string(url: &str) -> UrlType {
-        if url.contains(INSTAGRAM_URL) || url.contains(INSTAGRAM_NWWW_URL) {
-            return UrlType::Instagram;
-        } else if url.contains(DDINSTAGRAM_URL) {
-            return UrlType::DDInstagram;
-        }
-        UrlType::Unknown
+    /// `/share/<code>`, `/share/p/<code>` or `/share/reel/<code>`, whose code isn't the one of the post
+    fn is_share_link(&self, url: &Url) -> bool {
+        matches!(
+            path_segments(url)[..],
+            ["share", _] | ["share", "p" | "reel", _]
+        )
     }
 }
 
-pub fn is_instagram_url(url: &str) -> bool {
-    UrlType::from_string(url) == UrlType::Instagram
-}
+#[async_trait]
+impl Provider for Instagram {
+    fn id(&self) -> &'static str {
+        ID
+    }
 
-pub async fn convert_url(url: String, from: UrlType, to: UrlType) -> String {
-    url.replace(from.as_str(), to.as_str())
-}
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
 
-pub async fn convert_url_lazy(url: String, to: UrlType) -> String {
-    let from = UrlType::from_string(&url);
-    url.replace(from.as_str(), to.as_str())
-}
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
 
-pub async fn get_url_type(url: String) -> UrlType {
-    UrlType::from_string(&url)
-}
+    /// `igsh` and `igshid` identify who shared the link
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &TRACKING_PARAMETERS
+    }
+
+    /// Posts, reels and tv (`/p/<code>`, `/reel/<code>`, `/reels/<code>`, `/tv/<code>`,
+    /// optionally prefixed with the username), `/stories/<user>/<id>` or a share link
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            ["p" | "reel" | "reels" | "tv", _, ..] => true,
+            [_, "p" | "reel" | "reels" | "tv", _, ..] => true,
+            ["stories", user, id, ..] => {
+                user != "highlights" && id.bytes().all(|byte| byte.is_ascii_digit())
+            }
+            _ => self.is_share_link(url),
+        }
+    }
+
+    /// `/p/<code>`, `/reel/<code>` or `/stories/<user>/<id>` without the username prefix or share tracking
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = to_original(self, url);
+
+        let path = match path_segments(&canonical)[..] {
+            ["share", ..] => None,
+            ["p" | "tv", code, ..] | [_, "p" | "tv", code, ..] => Some(format!("/p/{}/", code)),
+            ["reel" | "reels", code, ..] | [_, "reel" | "reels", code, ..] => {
+                Some(format!("/reel/{}/", code))
+            }
+            _ => None,
+        };
+        if let Some(path) = path {
+            canonical.set_path(&path);
+        }
 
-pub async fn get_media_from_url(mut url: String) -> String {
-    url = convert_url_lazy(url, UrlType::DDInstagram).await;
-    crate::get_media(url).await
+        remove_tracking(&canonical, &TRACKING_PARAMETERS)
+    }
+
+    /// The shortcode, which is the same for posts and reels, or the id of a story
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            ["p" | "reel", code, ..] => Some(code.to_string()),
+            ["stories", _, id, ..] => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Follow share links to the post they were created for
+    async fn resolve(&self, client: &ThoriumClient, url: &Url) -> Result<Url> {
+        let share_link = self.canonicalize(url);
+        if !self.is_share_link(&share_link) {
+            return Ok(url.clone());
+        }
+
+        self.short_links
+            .resolve(client, &share_link, |post| {
+                let is_post = has_host(post, self.hosts()) && !self.is_share_link(post);
+                (is_post && self.is_post(post)).then(|| self.canonicalize(post))
+            })
+            .await
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let post = self.resolve(client, url).await?;
+        crate::get_media(client, self.media_url(&post).to_string()).await
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let post = self.resolve(client, url).await?;
+        crate::get_post(client, self.media_url(&post).to_string()).await
+    }
 }
@@ -4,46 +4,125 @@ pub mod twitter;
 pub mod bluesky;
 pub mod instagram;
 pub mod tiktok;
+pub mod reddit;
+pub mod threads;
+pub mod pixiv;
+pub mod tumblr;
+pub mod twitch;
+pub mod youtube;
+pub mod fediverse;
+pub mod generic;
 pub mod db;
+pub mod provider;
+pub mod links;
+pub mod error;
+pub mod media;
+pub mod post;
+pub mod client;
+pub mod fetcher;
+pub mod config;
+pub mod health;
+pub mod cache;
+pub mod tracking;
+pub mod mosaic;
+pub mod download;
 
-const USER_AGENT: &str = "Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com)";
+pub use client::{ClientConfig, ThoriumClient};
+pub use config::ThoriumConfig;
+pub use error::{Result, ThoriumError};
+pub use media::{MediaBundle, MediaItem, MediaKind};
+pub use post::PostInfo;
 
-pub async fn get_media(mut url: String) -> String {
-    // We don't want to follow the redirect so we can get the metadata
-    let client = reqwest::Client::builder()
-        .user_agent(USER_AGENT)
-        .build()
-        .unwrap();
+/// Collect the media announced by the OpenGraph meta tags of a page.
+/// Videos are preferred, images then only serve as their thumbnails.
+pub(crate) async fn scrape_media(
+    content: String,
+    image_property: &'static str,
+) -> Result<Option<MediaBundle>> {
+    // Parsing the page is blocking, so it happens in its own thread
+    spawn_blocking(move || {
+        let selector = scraper::Selector::parse("meta[content]").unwrap();
+        let html = scraper::Html::parse_document(content.as_str());
 
-    let request = client
-        .get(&url)
-        .header("user-agent", USER_AGENT)
-        .send()
-        .await
-        .unwrap();
+        let mut videos: Vec<MediaItem> = Vec::new();
+        let mut images: Vec<MediaItem> = Vec::new();
+        let mut audios: Vec<MediaItem> = Vec::new();
+        let mut found_empty_tag = false;
 
-    let content = request.text().await.unwrap();
+        for tag in html.select(&selector) {
+            let property = match tag.value().attr("property").or(tag.value().attr("name")) {
+                Some(property) => property,
+                None => continue,
+            };
+            let content = tag.value().attr("content").unwrap_or_default().trim();
 
-    // Check if content has a meta property and return it in a blocking thread
-    url = spawn_blocking(move || {
-        let selector_img = scraper::Selector::parse("meta[property='og:image']").unwrap();
-        let selector_video = scraper::Selector::parse("meta[property='og:video']").unwrap();
-        let html = scraper::Html::parse_document(content.as_str());
-        let vid = html.select(&selector_video).next();
-        let img = html.select(&selector_img).next();
-        if vid.is_none() && img.is_none() {
-            return "0".to_string();
+            let (items, kind, field) = match property {
+                "og:video" | "og:video:url" => (&mut videos, MediaKind::Video, None),
+                "og:audio" | "og:audio:url" => (&mut audios, MediaKind::Audio, None),
+                _ if property == image_property || property == "og:image:url" => {
+                    (&mut images, MediaKind::Image, None)
+                }
+                _ => match property.rsplit_once(':') {
+                    Some(("og:video", field)) => (&mut videos, MediaKind::Video, Some(field)),
+                    Some(("og:audio", field)) => (&mut audios, MediaKind::Audio, Some(field)),
+                    Some(("og:image", field)) => (&mut images, MediaKind::Image, Some(field)),
+                    Some((prefix, field)) if prefix == image_property => {
+                        (&mut images, MediaKind::Image, Some(field))
+                    }
+                    _ => continue,
+                },
+            };
+
+            match (field, items.last_mut()) {
+                (None, _) if content.is_empty() => found_empty_tag = true,
+                (None, _) => items.push(MediaItem::new(content, kind)),
+                (Some("secure_url"), Some(item)) => item.url = content.to_string(),
+                (Some("type"), Some(item)) => {
+                    item.kind = MediaKind::from_mime(content).unwrap_or(item.kind);
+                    item.mime_type = Some(content.to_string());
+                }
+                (Some("width"), Some(item)) => item.width = content.parse().ok(),
+                (Some("height"), Some(item)) => item.height = content.parse().ok(),
+                (Some("alt"), Some(item)) => item.alt_text = Some(content.to_string()),
+                _ => {}
+            }
         }
 
-        let url = if let Some(vid) = vid {
-            vid
+        // Embedded players (e.g. YouTube) are announced as HTML videos
+        videos.retain(|video| video.mime_type.as_deref() != Some("text/html"));
+
+        let items = if videos.is_empty() && audios.is_empty() {
+            images
         } else {
-            img.unwrap()
+            for video in &mut videos {
+                video.thumbnail = images.first().map(|image| image.url.clone());
+            }
+            videos.into_iter().chain(audios).collect()
         };
 
-        url.value().attr("content").unwrap().to_string()
+        match MediaBundle::new(items).non_empty() {
+            None if found_empty_tag => Err(ThoriumError::NoMedia),
+            bundle => Ok(bundle),
+        }
     })
-    .await
-    .unwrap();
-    url
-}
\ No newline at end of file
+    .await?
+}
+
+pub async fn get_media(client: &ThoriumClient, url: String) -> Result<Option<MediaBundle>> {
+    if links::parse(&url).is_none() {
+        return Err(ThoriumError::UnsupportedUrl(url));
+    }
+
+    let content = client.get_text(&url).await?;
+    scrape_media(content, "og:image").await
+}
+
+pub async fn get_post(client: &ThoriumClient, url: String) -> Result<Option<PostInfo>> {
+    if links::parse(&url).is_none() {
+        return Err(ThoriumError::UnsupportedUrl(url));
+    }
+
+    let content = client.get_text(&url).await?;
+    // Parsing the page is blocking, so it happens in its own thread
+    Ok(spawn_blocking(move || post::scrape_post(&content)).await?)
+}
@@ -0,0 +1,206 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::Range,
+};
+
+use url::{Host, Url};
+
+/// Characters that commonly trail a link in chat messages without being part of it
+const TRAILING_PUNCTUATION: &[char] = &[
+    '>', '|', ']', ',', '.', '!', '?', ';', ':', '"', '\'', '*', '_', '~',
+];
+
+/// A link found in a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// Byte range of the link inside the text, without the markdown around it
+    pub range: Range<usize>,
+    pub url: Url,
+    /// Between `||` spoiler markers
+    pub spoiler: bool,
+    /// Wrapped in `<>`, which Discord doesn't embed
+    pub suppressed: bool,
+    /// Text shown instead of the link in a masked link, `[label](url)`
+    pub label: Option<String>,
+}
+
+impl Link {
+    fn new(text: &str, range: Range<usize>, url: Url) -> Self {
+        let (before, after) = (&text[..range.start], &text[range.end..]);
+        let suppressed = before.ends_with('<') && after.starts_with('>');
+
+        // Masked links may be suppressed as well, `[label](<url>)`
+        let (inner_before, inner_after) = if suppressed {
+            (&before[..before.len() - 1], &after[1..])
+        } else {
+            (before, after)
+        };
+        let label = inner_before
+            .strip_suffix("](")
+            .filter(|_| inner_after.starts_with(')'))
+            .and_then(|before| before.rfind('[').map(|open| before[open + 1..].to_string()));
+
+        Self {
+            spoiler: before.matches("||").count() % 2 == 1 && after.contains("||"),
+            suppressed,
+            label,
+            range,
+            url,
+        }
+    }
+}
+
+/// Parse a single link, only http(s) links are accepted
+pub fn parse(link: &str) -> Option<Url> {
+    let url = Url::parse(link).ok()?;
+    match url.scheme() {
+        "http" | "https" if url.host_str().is_some() => Some(url),
+        _ => None,
+    }
+}
+
+/// Find every http(s) link in a text
+pub fn find_links(text: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut offset = 0;
+
+    for token in text.split_inclusive(char::is_whitespace) {
+        let start_of_token = offset;
+        offset += token.len();
+        let token = token.trim_end();
+
+        // Lowercasing ASCII keeps byte offsets intact
+        let lowercase = token.to_ascii_lowercase();
+        let start = match lowercase
+            .find("http://")
+            .or_else(|| lowercase.find("https://"))
+        {
+            Some(start) => start,
+            None => continue,
+        };
+
+        // Ignore links glued to a word, e.g. `foohttps://...`
+        if token[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+        {
+            continue;
+        }
+
+        let candidate = trim_link(&token[start..]);
+        if let Some(url) = parse(candidate) {
+            let start = start_of_token + start;
+            links.push(Link::new(text, start..start + candidate.len(), url));
+        }
+    }
+
+    links
+}
+
+/// Strip punctuation and markdown that directly follows a link
+fn trim_link(link: &str) -> &str {
+    let mut link = link;
+    loop {
+        let trimmed = link.trim_end_matches(TRAILING_PUNCTUATION);
+        // Only strip a closing parenthesis if it isn't part of the link, e.g. `(https://...)`
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(stripped) if trimmed.matches('(').count() < trimmed.matches(')').count() => {
+                stripped
+            }
+            _ => trimmed,
+        };
+
+        if trimmed.len() == link.len() {
+            return link;
+        }
+        link = trimmed;
+    }
+}
+
+/// Replace every link in the text for which `replace` returns a new url, leaving the rest untouched
+pub fn replace_links(text: &str, mut replace: impl FnMut(&Url) -> Option<Url>) -> String {
+    rewrite_links(text, |link| replace(&link.url).map(String::from))
+}
+
+/// Replace every link in the text for which `replace` returns a new text.
+/// Spoiler markers, angle brackets and labels around the links stay where they are.
+pub fn rewrite_links(text: &str, mut replace: impl FnMut(&Link) -> Option<String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for link in find_links(text) {
+        if let Some(replacement) = replace(&link) {
+            result.push_str(&text[last..link.range.start]);
+            result.push_str(&replacement);
+            last = link.range.end;
+        }
+    }
+
+    result.push_str(&text[last..]);
+    result
+}
+
+/// Whether the host of the url is one of the given hosts
+pub fn has_host(url: &Url, hosts: &[&str]) -> bool {
+    url.host_str().is_some_and(|host| {
+        hosts
+            .iter()
+            .any(|candidate| host.eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// Non-empty segments of the url path
+pub fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Whether the url is http(s) on a host of the public internet.
+/// Loopback, private, link-local and unique-local addresses are refused, as well as single-label hosts such as `localhost`.
+pub fn is_public(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain.contains('.') && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(address)) => is_public_ip(address.into()),
+        Some(Host::Ipv6(address)) => is_public_ip(address.into()),
+        None => false,
+    }
+}
+
+/// Whether the address belongs to the public internet, see [`is_public`]
+pub fn is_public_ip(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_public_ipv4(address),
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => is_public_ipv4(address),
+            None => is_public_ipv6(address),
+        },
+    }
+}
+
+fn is_public_ipv4(address: Ipv4Addr) -> bool {
+    !(address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_unspecified()
+        || address.is_broadcast()
+        // Shared address space of carrier-grade NAT, 100.64.0.0/10
+        || address.octets()[0] == 100 && address.octets()[1] & 0xC0 == 64)
+}
+
+fn is_public_ipv6(address: Ipv6Addr) -> bool {
+    let first = address.segments()[0];
+    !(address.is_loopback()
+        || address.is_unspecified()
+        // Unique local, fc00::/7
+        || first & 0xFE00 == 0xFC00
+        // Link-local, fe80::/10
+        || first & 0xFFC0 == 0xFE80)
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Gif,
+    Audio,
+}
+
+impl MediaKind {
+    /// Guess the kind from a MIME type such as `video/mp4`
+    pub fn from_mime(mime: &str) -> Option<MediaKind> {
+        if mime.eq_ignore_ascii_case("image/gif") {
+            return Some(MediaKind::Gif);
+        }
+
+        match mime.split('/').next()? {
+            "image" => Some(MediaKind::Image),
+            "video" => Some(MediaKind::Video),
+            "audio" => Some(MediaKind::Audio),
+            _ => None,
+        }
+    }
+
+    /// Guess the kind from the file extension of a link, defaults to an image
+    pub fn from_url(url: &str) -> MediaKind {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("mp4" | "webm" | "mov" | "m4v" | "m3u8") => MediaKind::Video,
+            Some("gif") => MediaKind::Gif,
+            Some("mp3" | "m4a" | "ogg" | "opus" | "wav") => MediaKind::Audio,
+            _ => MediaKind::Image,
+        }
+    }
+}
+
+/// A single image, video, gif or audio file of a post
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaItem {
+    /// Direct link to the media
+    pub url: String,
+    pub kind: MediaKind,
+    pub mime_type: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Duration in seconds for videos, gifs and audio
+    pub duration: Option<f64>,
+    /// Link to a preview image
+    pub thumbnail: Option<String>,
+    pub alt_text: Option<String>,
+}
+
+impl MediaItem {
+    pub fn new(url: impl Into<String>, kind: MediaKind) -> Self {
+        Self {
+            url: url.into(),
+            kind,
+            mime_type: None,
+            width: None,
+            height: None,
+            duration: None,
+            thumbnail: None,
+            alt_text: None,
+        }
+    }
+
+    /// Media item whose kind is guessed from the extension of the link
+    pub fn from_url(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let kind = MediaKind::from_url(&url);
+        Self::new(url, kind)
+    }
+}
+
+/// Every media item found behind a post
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaBundle {
+    pub items: Vec<MediaItem>,
+    /// Link to a single image combining all images, if the source provides one
+    pub mosaic: Option<String>,
+}
+
+impl MediaBundle {
+    pub fn new(items: Vec<MediaItem>) -> Self {
+        Self {
+            items,
+            mosaic: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&MediaItem> {
+        self.items.first()
+    }
+
+    /// Links of every item
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(|item| item.url.as_str())
+    }
+
+    /// None if the bundle holds no items
+    pub fn non_empty(self) -> Option<Self> {
+        (!self.is_empty()).then_some(self)
+    }
+}
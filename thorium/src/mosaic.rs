@@ -0,0 +1,262 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::{self, FilterType},
+    DynamicImage, ImageReader, Limits, Rgb, RgbImage,
+};
+use tokio::task::spawn_blocking;
+
+use crate::download::{download_media, DownloadLimits, DEFAULT_MAX_BYTES};
+use crate::{MediaBundle, MediaItem, MediaKind, Result, ThoriumClient, ThoriumError};
+
+/// Most images a mosaic has room for, further ones are left out
+pub const MAX_IMAGES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MosaicFormat {
+    Jpeg,
+    /// Lossless, larger than JPEG but without artifacts
+    WebP,
+}
+
+impl MosaicFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MosaicFormat::Jpeg => "jpg",
+            MosaicFormat::WebP => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            MosaicFormat::Jpeg => "image/jpeg",
+            MosaicFormat::WebP => "image/webp",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MosaicConfig {
+    /// Size of the whole mosaic, images are scaled down to fit
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Space between the images
+    pub gap: u32,
+    pub format: MosaicFormat,
+    /// JPEG quality to start with, lowered until the mosaic fits in `max_bytes`
+    pub quality: u8,
+    /// Largest file that can be uploaded, e.g. to Discord
+    pub max_bytes: usize,
+    /// Largest image that is downloaded, the download stops as soon as an image grows past it
+    pub max_image_bytes: u64,
+    /// Largest width and height of a decoded image, small files can still decode to huge images
+    pub max_image_dimension: u32,
+}
+
+impl Default for MosaicConfig {
+    fn default() -> Self {
+        Self {
+            max_width: 2048,
+            max_height: 2048,
+            gap: 4,
+            format: MosaicFormat::Jpeg,
+            quality: 85,
+            max_bytes: 8 * 1024 * 1024,
+            max_image_bytes: DEFAULT_MAX_BYTES,
+            max_image_dimension: 8192,
+        }
+    }
+}
+
+/// Encoded image combining the images of a post
+#[derive(Debug, Clone)]
+pub struct Mosaic {
+    pub data: Vec<u8>,
+    pub format: MosaicFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Mosaic {
+    /// Name to upload the mosaic as
+    pub fn file_name(&self) -> String {
+        format!("mosaic.{}", self.format.extension())
+    }
+}
+
+/// Place of an image inside the mosaic
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Download the images of the bundle and combine them, None if it has less than two of them
+pub async fn compose(
+    client: &ThoriumClient,
+    bundle: &MediaBundle,
+    config: &MosaicConfig,
+) -> Result<Option<Mosaic>> {
+    let items: Vec<&MediaItem> = bundle
+        .items
+        .iter()
+        .filter(|item| item.kind == MediaKind::Image)
+        .take(MAX_IMAGES)
+        .collect();
+    if items.len() < 2 {
+        return Ok(None);
+    }
+
+    let limits = DownloadLimits::new(config.max_image_bytes);
+    let mut files = Vec::with_capacity(items.len());
+    for item in items {
+        let download = download_media(client, item, &limits).await?;
+        files.push(download.into_bytes().unwrap_or_default());
+    }
+
+    // Decoding and encoding images is blocking, so it happens in its own thread
+    let config = config.clone();
+    spawn_blocking(move || {
+        let images = files
+            .iter()
+            .map(|file| decode(file, &config))
+            .collect::<Result<Vec<_>>>()?;
+        compose_images(&images, &config).map(Some)
+    })
+    .await?
+}
+
+/// Decode the image, refusing images larger than the config allows before they are allocated
+fn decode(file: &[u8], config: &MosaicConfig) -> Result<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(config.max_image_dimension);
+    limits.max_image_height = Some(config.max_image_dimension);
+
+    let mut reader = ImageReader::new(Cursor::new(file)).with_guessed_format()?;
+    reader.limits(limits);
+    Ok(reader.decode()?)
+}
+
+/// Combine two to four images into a grid: side by side, one large with two stacked next to it, or two by two
+pub fn compose_images(images: &[DynamicImage], config: &MosaicConfig) -> Result<Mosaic> {
+    let images = &images[..images.len().min(MAX_IMAGES)];
+    let cells = layout(images, config)
+        .ok_or_else(|| ThoriumError::Parse("a mosaic needs at least two images".to_string()))?;
+
+    let width = cells
+        .iter()
+        .map(|cell| cell.x + cell.width)
+        .max()
+        .unwrap_or_default();
+    let height = cells
+        .iter()
+        .map(|cell| cell.y + cell.height)
+        .max()
+        .unwrap_or_default();
+    let mut canvas = RgbImage::from_pixel(width, height, Rgb([0, 0, 0]));
+
+    // Images are cropped to fill their cell, like the grids of the sites themselves
+    for (image, cell) in images.iter().zip(&cells) {
+        let resized = image
+            .resize_to_fill(cell.width, cell.height, FilterType::Triangle)
+            .to_rgb8();
+        imageops::replace(&mut canvas, &resized, cell.x.into(), cell.y.into());
+    }
+
+    encode(canvas, config)
+}
+
+/// Cells of the images, None for layouts that don't exist
+fn layout(images: &[DynamicImage], config: &MosaicConfig) -> Option<Vec<Cell>> {
+    let rows = match images.len() {
+        2 => 1,
+        3 | 4 => 2,
+        _ => return None,
+    };
+    let gap = config.gap;
+
+    // Cells are as wide as the widest image allows and as tall as the images are on average
+    let widest = images.iter().map(DynamicImage::width).max()?;
+    let cell_width = widest.min(config.max_width.saturating_sub(gap) / 2).max(1);
+    let aspect_ratio = images
+        .iter()
+        .map(|image| image.height() as f64 / image.width().max(1) as f64)
+        .sum::<f64>()
+        / images.len() as f64;
+    let max_cell_height = config.max_height.saturating_sub(gap * (rows - 1)) / rows;
+    let cell_height = ((cell_width as f64 * aspect_ratio) as u32).clamp(1, max_cell_height.max(1));
+
+    let column = cell_width + gap;
+    let row = cell_height + gap;
+    let cell = |x: u32, y: u32, height: u32| Cell {
+        x,
+        y,
+        width: cell_width,
+        height,
+    };
+
+    Some(match images.len() {
+        2 => vec![cell(0, 0, cell_height), cell(column, 0, cell_height)],
+        // The first image takes the whole left column
+        3 => vec![
+            cell(0, 0, cell_height * 2 + gap),
+            cell(column, 0, cell_height),
+            cell(column, row, cell_height),
+        ],
+        _ => vec![
+            cell(0, 0, cell_height),
+            cell(column, 0, cell_height),
+            cell(0, row, cell_height),
+            cell(column, row, cell_height),
+        ],
+    })
+}
+
+fn encode(canvas: RgbImage, config: &MosaicConfig) -> Result<Mosaic> {
+    let (width, height) = canvas.dimensions();
+    let mosaic = |data| Mosaic {
+        data,
+        format: config.format,
+        width,
+        height,
+    };
+
+    match config.format {
+        MosaicFormat::Jpeg => {
+            // Lower the quality until the mosaic is small enough to be uploaded
+            let mut quality = config.quality.clamp(1, 100);
+            loop {
+                let mut data = Vec::new();
+                JpegEncoder::new_with_quality(&mut data, quality).encode_image(&canvas)?;
+                if data.len() <= config.max_bytes || quality <= 30 {
+                    return fits(mosaic(data), config);
+                }
+                quality -= 10;
+            }
+        }
+        MosaicFormat::WebP => {
+            let mut data = Cursor::new(Vec::new());
+            WebPEncoder::new_lossless(&mut data).encode(
+                canvas.as_raw(),
+                width,
+                height,
+                image::ExtendedColorType::Rgb8,
+            )?;
+            fits(mosaic(data.into_inner()), config)
+        }
+    }
+}
+
+fn fits(mosaic: Mosaic, config: &MosaicConfig) -> Result<Mosaic> {
+    if mosaic.data.len() > config.max_bytes {
+        return Err(ThoriumError::Parse(format!(
+            "mosaic of {} bytes is larger than {} bytes",
+            mosaic.data.len(),
+            config.max_bytes
+        )));
+    }
+    Ok(mosaic)
+}
@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use url::Url;
+
+use crate::links::path_segments;
+use crate::post::html_to_text;
+use crate::provider::{Mirror, Provider};
+use crate::{MediaBundle, MediaItem, PostInfo, Result, ThoriumClient, ThoriumConfig, ThoriumError};
+
+pub const ID: &str = "pixiv";
+
+pub const PIXIV_HOST: &str = "www.pixiv.net";
+
+/// Id of the mirror
+pub const PHIXIV: &str = "phixiv";
+/// Id of the API, configured like mirrors
+pub const PHIXIV_API: &str = "phixiv_api";
+
+pub const PHIXIV_URL: &str = "https://www.phixiv.net/";
+pub const PHIXIV_API_URL: &str = "https://www.phixiv.net/";
+
+static HOSTS: [&str; 2] = [PIXIV_HOST, "pixiv.net"];
+
+pub struct Pixiv {
+    mirrors: Vec<Mirror>,
+    api_url: Url,
+}
+
+impl Pixiv {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![Mirror::new(PHIXIV, PHIXIV_URL, config)],
+            api_url: config.url(PHIXIV_API, PHIXIV_API_URL),
+        }
+    }
+
+    /// Artwork as described by the info API of phixiv, which has a proxied link for every page
+    async fn fetch_artwork(&self, client: &ThoriumClient, url: &Url) -> Result<Value> {
+        let canonical = self.canonicalize(url);
+        let id = self
+            .post_id(&canonical)
+            .ok_or_else(|| ThoriumError::UnsupportedUrl(url.to_string()))?;
+
+        let mut api_url = format!(
+            "{}/api/info?id={}",
+            self.api_url.as_str().trim_end_matches('/'),
+            id
+        );
+        // Titles and tags are translated when the link asks for a language
+        if let [language, "artworks", _] = path_segments(&canonical)[..] {
+            api_url.push_str(&format!("&language={}", language));
+        }
+
+        Ok(serde_json::from_str(&client.get_text(&api_url).await?)?)
+    }
+}
+
+#[async_trait]
+impl Provider for Pixiv {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// `/artworks/<id>`, optionally prefixed with a language such as `/en/`
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            ["artworks", id] | [_, "artworks", id] => {
+                !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit())
+            }
+            _ => false,
+        }
+    }
+
+    /// The numeric id of the artwork, regardless of the language
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            ["artworks", id] | [_, "artworks", id] => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Every page of the artwork
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let artwork = self.fetch_artwork(client, url).await?;
+        let pages = artwork["image_proxy_urls"].as_array().cloned();
+        let items = pages
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|page| page.as_str().map(MediaItem::from_url))
+            .collect();
+        Ok(MediaBundle::new(items).non_empty())
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let artwork = self.fetch_artwork(client, url).await?;
+        let string = |value: &Value| {
+            value
+                .as_str()
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+
+        let description = string(&artwork["description"])
+            .map(|description| html_to_text(&description))
+            .filter(|description| !description.is_empty());
+        let text = match (string(&artwork["title"]), description) {
+            (Some(title), Some(description)) => Some(format!("{}\n\n{}", title, description)),
+            (title, description) => title.or(description),
+        };
+
+        let post = PostInfo {
+            author_name: string(&artwork["author_name"]),
+            text,
+            ..Default::default()
+        };
+        Ok((!post.is_empty()).then_some(post))
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Metadata of a post, every field is optional as sources expose different subsets
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostInfo {
+    pub author_name: Option<String>,
+    /// Handle of the author without a leading `@`
+    pub author_handle: Option<String>,
+    pub text: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub replies: Option<u64>,
+    pub reposts: Option<u64>,
+    pub likes: Option<u64>,
+    pub quoted: Option<Box<PostInfo>>,
+    /// Language of the text as reported by the source (e.g. `en`)
+    pub language: Option<String>,
+}
+
+impl PostInfo {
+    pub fn is_empty(&self) -> bool {
+        *self == PostInfo::default()
+    }
+
+    /// Fill every missing field from another source
+    pub fn merge(mut self, other: PostInfo) -> PostInfo {
+        self.author_name = self.author_name.or(other.author_name);
+        self.author_handle = self.author_handle.or(other.author_handle);
+        self.text = self.text.or(other.text);
+        self.created_at = self.created_at.or(other.created_at);
+        self.replies = self.replies.or(other.replies);
+        self.reposts = self.reposts.or(other.reposts);
+        self.likes = self.likes.or(other.likes);
+        self.quoted = self.quoted.or(other.quoted);
+        self.language = self.language.or(other.language);
+        self
+    }
+}
+
+/// Read the post metadata from the JSON-LD and OpenGraph tags of a page.
+/// JSON-LD is more precise, so OpenGraph only fills the gaps.
+pub(crate) fn scrape_post(content: &str) -> Option<PostInfo> {
+    let html = scraper::Html::parse_document(content);
+
+    let json_ld = scraper::Selector::parse("script[type='application/ld+json']").unwrap();
+    let from_json_ld = html
+        .select(&json_ld)
+        .filter_map(|script| serde_json::from_str::<Value>(&script.text().collect::<String>()).ok())
+        .find_map(|json| post_from_json_ld(&json))
+        .unwrap_or_default();
+
+    let meta = |property: &str| {
+        let selector = scraper::Selector::parse(&format!(
+            "meta[property='{0}'][content], meta[name='{0}'][content]",
+            property
+        ))
+        .unwrap();
+        html.select(&selector)
+            .next()
+            .and_then(|tag| tag.value().attr("content"))
+            .map(str::trim)
+            .filter(|content| !content.is_empty())
+            .map(str::to_string)
+    };
+
+    let (author_name, author_handle) = match meta("og:title") {
+        Some(title) => split_title(&title),
+        None => (None, None),
+    };
+
+    let from_open_graph = PostInfo {
+        author_name,
+        author_handle,
+        text: meta("og:description").or_else(|| meta("description")),
+        created_at: meta("article:published_time").and_then(|time| parse_time(&time)),
+        language: meta("og:locale"),
+        ..Default::default()
+    };
+
+    let post = from_json_ld.merge(from_open_graph);
+    (!post.is_empty()).then_some(post)
+}
+
+/// Split titles like `Name (@handle)` into name and handle
+fn split_title(title: &str) -> (Option<String>, Option<String>) {
+    match title.rsplit_once(" (@") {
+        Some((name, handle)) => (
+            Some(name.trim().to_string()),
+            handle.strip_suffix(')').map(str::to_string),
+        ),
+        None => (Some(title.to_string()), None),
+    }
+}
+
+/// Text of HTML such as the paragraphs of a status, keeping its line breaks
+pub(crate) fn html_to_text(html: &str) -> String {
+    let html = html
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("</p>", "</p>\n\n");
+    scraper::Html::parse_fragment(&html)
+        .root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+pub(crate) fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .or_else(|_| DateTime::parse_from_rfc2822(time))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn post_from_json_ld(json: &Value) -> Option<PostInfo> {
+    // Pages may wrap their objects in a list or a graph
+    if let Some(items) = json.as_array().or_else(|| json["@graph"].as_array()) {
+        return items.iter().find_map(post_from_json_ld);
+    }
+
+    let kind = json["@type"].as_str()?;
+    if !matches!(
+        kind,
+        "SocialMediaPosting" | "DiscussionForumPosting" | "BlogPosting" | "Article" | "VideoObject"
+    ) {
+        return None;
+    }
+
+    let string = |value: &Value| value.as_str().map(str::to_string);
+    let author = match &json["author"] {
+        Value::Array(authors) => authors.first().cloned().unwrap_or_default(),
+        author => author.clone(),
+    };
+
+    let mut post = PostInfo {
+        author_name: string(&author["name"]),
+        author_handle: string(&author["alternateName"])
+            .or_else(|| string(&author["identifier"]))
+            .map(|handle| handle.trim_start_matches('@').to_string()),
+        text: string(&json["articleBody"])
+            .or_else(|| string(&json["text"]))
+            .or_else(|| string(&json["description"])),
+        created_at: json["datePublished"].as_str().and_then(parse_time),
+        language: string(&json["inLanguage"]),
+        quoted: json["sharedContent"]
+            .as_object()
+            .and_then(|_| post_from_json_ld(&json["sharedContent"]))
+            .map(Box::new),
+        ..Default::default()
+    };
+
+    let statistics = match &json["interactionStatistic"] {
+        Value::Array(statistics) => statistics.clone(),
+        statistic => vec![statistic.clone()],
+    };
+    for statistic in statistics {
+        let count = statistic["userInteractionCount"].as_u64();
+        let kind = statistic["interactionType"]
+            .as_str()
+            .or_else(|| statistic["interactionType"]["@type"].as_str())
+            .and_then(|kind| kind.rsplit('/').next());
+
+        match kind {
+            Some("LikeAction") => post.likes = count,
+            Some("ShareAction") => post.reposts = count,
+            Some("CommentAction") => post.replies = count,
+            _ => {}
+        }
+    }
+
+    Some(post)
+}
@@ -0,0 +1,523 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use url::Url;
+
+use crate::{
+    bluesky::Bluesky,
+    cache::{BoundedMap, MediaCache},
+    fediverse::Fediverse,
+    generic::Generic,
+    health::{HealthConfig, MirrorHealth},
+    instagram::Instagram,
+    links::{self, has_host, Link},
+    pixiv::Pixiv,
+    reddit::Reddit,
+    threads::Threads,
+    tiktok::TikTok,
+    tumblr::Tumblr,
+    twitch::Twitch,
+    tracking,
+    twitter::Twitter,
+    youtube::YouTube,
+    MediaBundle, PostInfo, Result, ThoriumClient, ThoriumConfig, ThoriumError,
+};
+
+/// A frontend that links of a provider can be rewritten to (e.g. vxtwitter for Twitter)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mirror {
+    /// Stable identifier, also used as the value of select menu options and as config key
+    pub id: &'static str,
+    /// Links are rewritten onto this url, e.g. `https://vxtwitter.com/`
+    pub base_url: Url,
+}
+
+impl Mirror {
+    /// Mirror at the url configured for its id, or at the default one
+    pub fn new(id: &'static str, default_url: &str, config: &ThoriumConfig) -> Self {
+        Self {
+            id,
+            base_url: config.url(id, default_url),
+        }
+    }
+
+    /// Mirror that only exists when its url is configured
+    pub fn configured(id: &'static str, config: &ThoriumConfig) -> Option<Self> {
+        Some(Self {
+            id,
+            base_url: config.configured_url(id)?,
+        })
+    }
+
+    /// Whether the url points to this mirror
+    pub fn serves(&self, url: &Url) -> bool {
+        let same_port = self
+            .base_url
+            .port()
+            .is_none_or(|port| url.port() == Some(port));
+        same_port && has_host(url, &[self.base_url.host_str().unwrap_or_default()])
+    }
+}
+
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Stable identifier of the provider (e.g. `twitter`)
+    fn id(&self) -> &'static str;
+
+    /// Hosts of the original site, the first one is the canonical one.
+    /// Providers that aren't bound to hosts return none and override the methods using them.
+    fn hosts(&self) -> &'static [&'static str];
+
+    /// Mirrors links can be rewritten to, the first one is the default.
+    /// Without mirrors links stay on the original site.
+    fn mirrors(&self) -> &[Mirror];
+
+    /// Whether the path of the url has the shape of a post on this site
+    fn is_post(&self, url: &Url) -> bool;
+
+    /// Identifier of the post that is the same for every link to it, used as cache key
+    fn post_id(&self, url: &Url) -> Option<String> {
+        let canonical = self.canonicalize(url);
+        self.is_post(&canonical)
+            .then(|| canonical.path().trim_matches('/').to_string())
+    }
+
+    /// Mirror that gets scraped when extracting media, the original site is scraped without one
+    fn media_mirror(&self) -> Option<&Mirror> {
+        self.default_mirror()
+    }
+
+    fn default_mirror(&self) -> Option<&Mirror> {
+        self.mirrors().first()
+    }
+
+    fn mirror(&self, id: &str) -> Option<&Mirror> {
+        self.mirrors().iter().find(|mirror| mirror.id == id)
+    }
+
+    /// Whether the url points to a post on the original site
+    fn detect(&self, url: &Url) -> bool {
+        has_host(url, self.hosts()) && self.is_post(url)
+    }
+
+    /// Whether the url points to a post on the original site or any of its mirrors
+    fn owns(&self, url: &Url) -> bool {
+        let on_mirror = self.mirrors().iter().any(|mirror| mirror.serves(url));
+        (has_host(url, self.hosts()) && self.is_post(url))
+            || (on_mirror && self.is_post(&self.canonicalize(url)))
+    }
+
+    /// Rewrite the url back to the original site
+    fn canonicalize(&self, url: &Url) -> Url {
+        to_original(self, url)
+    }
+
+    /// Rewrite the url to the given mirror
+    fn rewrite(&self, url: &Url, mirror: &Mirror) -> Url {
+        with_base(&self.canonicalize(url), &mirror.base_url)
+    }
+
+    /// Url that gets scraped when extracting media
+    fn media_url(&self, url: &Url) -> Url {
+        match self.media_mirror() {
+            Some(mirror) => self.rewrite(url, mirror),
+            None => self.canonicalize(url),
+        }
+    }
+
+    /// Mirror that getting the media or post of the url fetches from, its health follows the outcome.
+    /// Links on a mirror go to that mirror, others to the media mirror.
+    fn fetched_mirror(&self, url: &Url) -> Option<&Mirror> {
+        self.mirrors()
+            .iter()
+            .find(|mirror| mirror.serves(url))
+            .or(self.media_mirror())
+    }
+
+    /// Query parameters of the site that only track who shared the link, see [`tracking::remove_tracking`].
+    /// Parameters that change what the link shows, like a timestamp, are kept.
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Url of the post itself for links that only redirect to it, such as short links
+    async fn resolve(&self, _client: &ThoriumClient, url: &Url) -> Result<Url> {
+        Ok(url.clone())
+    }
+
+    /// Whether the url really points to a post, for providers that can't tell from the url alone
+    async fn verify(&self, _client: &ThoriumClient, _url: &Url) -> bool {
+        true
+    }
+
+    /// Extract the media of the post behind the url
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        crate::get_media(client, self.media_url(url).to_string()).await
+    }
+
+    /// Extract the author, text and statistics of the post behind the url
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        crate::get_post(client, self.media_url(url).to_string()).await
+    }
+}
+
+/// Copy of the url on the canonical host of the provider, this is what [`Provider::canonicalize`] does by default
+pub fn to_original<P: Provider + ?Sized>(provider: &P, url: &Url) -> Url {
+    let mut canonical = with_host(url, provider.hosts()[0]);
+    // Self-hosted mirrors may live below a path
+    let prefix = provider
+        .mirrors()
+        .iter()
+        .find(|mirror| mirror.serves(url))
+        .map(|mirror| mirror.base_url.path().trim_end_matches('/'));
+    if let Some(path) = prefix.and_then(|prefix| url.path().strip_prefix(prefix)) {
+        canonical.set_path(path);
+    }
+    canonical
+}
+
+/// Copy of the url pointing to another host, always using https on the default port
+pub fn with_host(url: &Url, host: &str) -> Url {
+    let mut url = url.clone();
+    // These only fail for urls that can't be http(s) links in the first place
+    let _ = url.set_scheme("https");
+    let _ = url.set_host(Some(host));
+    let _ = url.set_port(None);
+    url
+}
+
+/// Copy of the url moved onto the base url, whose path is kept as a prefix
+pub fn with_base(url: &Url, base: &Url) -> Url {
+    let mut rewritten = base.clone();
+    rewritten.set_path(&format!(
+        "{}{}",
+        base.path().trim_end_matches('/'),
+        url.path()
+    ));
+    rewritten.set_query(url.query());
+    rewritten.set_fragment(url.fragment());
+    rewritten
+}
+
+/// Most short links whose target is remembered
+const SHORT_LINK_CAPACITY: usize = 4096;
+/// Short links don't change where they point to, but the memory is only kept for a while
+const SHORT_LINK_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where short links redirected to, so every short link is only followed once
+pub struct ShortLinks {
+    resolved: BoundedMap<Url>,
+}
+
+impl Default for ShortLinks {
+    fn default() -> Self {
+        Self {
+            resolved: BoundedMap::new(SHORT_LINK_CAPACITY),
+        }
+    }
+}
+
+impl ShortLinks {
+    /// Follow the redirects of the short link, `accept` turns the final url into the post or rejects it
+    pub async fn resolve(
+        &self,
+        client: &ThoriumClient,
+        short_link: &Url,
+        accept: impl Fn(&Url) -> Option<Url>,
+    ) -> Result<Url> {
+        if let Some(post) = self.resolved.get(short_link.as_str()) {
+            return Ok(post);
+        }
+
+        let response = client.get(short_link.as_str()).await?;
+        let post = links::parse(&response.url)
+            .and_then(|url| accept(&url))
+            .ok_or_else(|| ThoriumError::UnsupportedUrl(short_link.to_string()))?;
+
+        self.resolved
+            .insert(short_link.as_str(), post.clone(), SHORT_LINK_TTL);
+        Ok(post)
+    }
+}
+
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn Provider>>,
+    /// Handles links no provider owns, only when asked to
+    fallback: Option<Box<dyn Provider>>,
+    client: ThoriumClient,
+    health: MirrorHealth,
+    cache: MediaCache,
+}
+
+impl ProviderRegistry {
+    /// Empty registry whose providers fetch through the given client
+    pub fn new(client: ThoriumClient) -> Self {
+        Self {
+            providers: Vec::new(),
+            fallback: None,
+            client,
+            health: MirrorHealth::default(),
+            cache: MediaCache::default(),
+        }
+    }
+
+    /// Remember media lookups in the given cache instead of the default in-memory one
+    pub fn with_cache(mut self, cache: MediaCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Track the health of the mirrors with other thresholds than the default ones
+    pub fn with_health_config(mut self, config: HealthConfig) -> Self {
+        self.health = MirrorHealth::new(config);
+        self
+    }
+
+    /// Handle links no provider owns with the given provider, see [`get_media_or_fallback`](Self::get_media_or_fallback)
+    pub fn with_fallback(mut self, fallback: impl Provider + 'static) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Registry with every provider shipped by thorium, using the mirrors of the config
+    pub fn with_builtin_providers(client: ThoriumClient, config: &ThoriumConfig) -> Self {
+        let mut registry = Self::new(client);
+        registry.register(Twitter::new(config));
+        registry.register(Bluesky::new(config));
+        registry.register(TikTok::new(config));
+        registry.register(Instagram::new(config));
+        registry.register(Reddit::new(config));
+        registry.register(Threads::new(config));
+        registry.register(Pixiv::new(config));
+        registry.register(Tumblr::new(config));
+        registry.register(Twitch::new());
+        registry.register(YouTube::new());
+        // Matches by the shape of the path alone, so it goes last
+        registry.register(Fediverse::new());
+        registry.with_fallback(Generic::new())
+    }
+
+    pub fn client(&self) -> &ThoriumClient {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &MediaCache {
+        &self.cache
+    }
+
+    pub fn health(&self) -> &MirrorHealth {
+        &self.health
+    }
+
+    pub fn register(&mut self, provider: impl Provider + 'static) {
+        self.providers.push(Box::new(provider));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Provider> {
+        self.providers.iter().map(|provider| provider.as_ref())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn Provider> {
+        self.iter().find(|provider| provider.id() == id)
+    }
+
+    pub fn fallback(&self) -> Option<&dyn Provider> {
+        self.fallback.as_deref()
+    }
+
+    /// Find the provider whose original site the url points to
+    pub fn detect(&self, url: &Url) -> Option<&dyn Provider> {
+        self.iter().find(|provider| provider.detect(url))
+    }
+
+    /// Like [`detect`](Self::detect), but also confirm the match with providers that need to ask the site
+    pub async fn detect_verified(&self, url: &Url) -> Option<&dyn Provider> {
+        for provider in self.iter().filter(|provider| provider.detect(url)) {
+            if provider.verify(&self.client, url).await {
+                return Some(provider);
+            }
+        }
+        None
+    }
+
+    /// Find the provider whose original site or mirrors the url points to
+    pub fn find(&self, url: &Url) -> Option<&dyn Provider> {
+        self.iter().find(|provider| provider.owns(url))
+    }
+
+    /// Like [`find`](Self::find), but links only owned once verified, such as Fediverse posts, are verified
+    pub async fn find_verified(&self, url: &Url) -> Option<&dyn Provider> {
+        match self.find(url) {
+            Some(provider) => Some(provider),
+            None => self.detect_verified(url).await,
+        }
+    }
+
+    /// Like [`find_verified`](Self::find_verified), but links no provider owns go to the fallback
+    pub async fn find_or_fallback(&self, url: &Url) -> Option<&dyn Provider> {
+        match self.find_verified(url).await {
+            Some(provider) => Some(provider),
+            None => self.fallback().filter(|fallback| fallback.owns(url)),
+        }
+    }
+
+    /// Follow the url to the post it stands for, using the provider that owns it
+    pub async fn resolve(&self, url: &Url) -> Result<Url> {
+        self.owner(url).await?.resolve(&self.client, url).await
+    }
+
+    /// Copy of the url without tracking parameters, using the rules of the provider that owns it
+    pub fn clean(&self, url: &Url) -> Url {
+        let rules = self
+            .find(url)
+            .map(|provider| provider.tracking_parameters())
+            .unwrap_or_default();
+        tracking::remove_tracking(url, rules)
+    }
+
+    /// Replace every link in the text by its [`clean`](Self::clean) copy
+    pub fn clean_links(&self, text: &str) -> String {
+        links::replace_links(text, |url| {
+            let cleaned = self.clean(url);
+            (cleaned != *url).then_some(cleaned)
+        })
+    }
+
+    /// Extract the media of the post behind the url using the provider that owns it
+    pub async fn get_media(&self, url: &Url) -> Result<Option<MediaBundle>> {
+        self.media_of(self.owner(url).await?, url).await
+    }
+
+    /// Like [`get_media`](Self::get_media), but the fallback scrapes the pages no provider owns
+    pub async fn get_media_or_fallback(&self, url: &Url) -> Result<Option<MediaBundle>> {
+        self.media_of(self.owner_or_fallback(url).await?, url).await
+    }
+
+    async fn media_of(&self, provider: &dyn Provider, url: &Url) -> Result<Option<MediaBundle>> {
+        // Short links and the links they stand for share their cache entry
+        let url = &provider.resolve(&self.client, url).await?;
+        let mirror = provider.fetched_mirror(url);
+        let key = provider.post_id(url).map(|post_id| match mirror {
+            Some(mirror) => MediaCache::mirror_key(provider.id(), mirror.id, &post_id),
+            None => MediaCache::key(provider.id(), &post_id),
+        });
+        if let Some(media) = key.as_deref().and_then(|key| self.cache.get(key)) {
+            return media;
+        }
+
+        let media = provider.get_media(&self.client, url).await;
+        if let Some(mirror) = mirror {
+            self.health.record(mirror.id, &media);
+        }
+        if let Some(key) = key {
+            self.cache.insert(&key, &media);
+        }
+        media
+    }
+
+    /// Extract the metadata of the post behind the url using the provider that owns it
+    pub async fn get_post(&self, url: &Url) -> Result<Option<PostInfo>> {
+        self.post_of(self.owner(url).await?, url).await
+    }
+
+    /// Like [`get_post`](Self::get_post), but the fallback scrapes the pages no provider owns
+    pub async fn get_post_or_fallback(&self, url: &Url) -> Result<Option<PostInfo>> {
+        self.post_of(self.owner_or_fallback(url).await?, url).await
+    }
+
+    async fn post_of(&self, provider: &dyn Provider, url: &Url) -> Result<Option<PostInfo>> {
+        let post = provider.get_post(&self.client, url).await;
+        if let Some(mirror) = provider.fetched_mirror(url) {
+            self.health.record(mirror.id, &post);
+        }
+        post
+    }
+
+    async fn owner(&self, url: &Url) -> Result<&dyn Provider> {
+        self.find_verified(url)
+            .await
+            .ok_or_else(|| ThoriumError::UnsupportedUrl(url.to_string()))
+    }
+
+    async fn owner_or_fallback(&self, url: &Url) -> Result<&dyn Provider> {
+        self.find_or_fallback(url)
+            .await
+            .ok_or_else(|| ThoriumError::UnsupportedUrl(url.to_string()))
+    }
+
+    /// Whether links should currently be rewritten to the mirror
+    pub fn is_healthy(&self, mirror: &Mirror) -> bool {
+        self.health.is_healthy(mirror.id)
+    }
+
+    /// First mirror of the provider that isn't down, or its default one if all of them are
+    pub fn preferred_mirror<'a>(&self, provider: &'a dyn Provider) -> Option<&'a Mirror> {
+        provider
+            .mirrors()
+            .iter()
+            .find(|mirror| self.is_healthy(mirror))
+            .or(provider.default_mirror())
+    }
+
+    /// Request the base url of every mirror and record whether it answered
+    pub async fn probe_mirrors(&self) {
+        for mirror in self.iter().flat_map(|provider| provider.mirrors()) {
+            let response = self.client.get(mirror.base_url.as_str()).await;
+            self.health.record(mirror.id, &response);
+        }
+    }
+
+    /// Probe the mirrors periodically, this never returns and is meant to be spawned as a task
+    pub async fn monitor_mirrors(&self) {
+        let mut interval = tokio::time::interval(self.health.config().probe_interval);
+        loop {
+            interval.tick().await;
+            self.probe_mirrors().await;
+        }
+    }
+
+    /// Find the first link in the text that points to the original site of a provider
+    pub fn detect_in(&self, text: &str) -> Option<(&dyn Provider, Url)> {
+        links::find_links(text)
+            .into_iter()
+            .find_map(|link| self.detect(&link.url).map(|provider| (provider, link.url)))
+    }
+
+    /// Find the first link in the text that points to a provider or one of its mirrors
+    pub fn find_in(&self, text: &str) -> Option<(&dyn Provider, Url)> {
+        links::find_links(text)
+            .into_iter()
+            .find_map(|link| self.find(&link.url).map(|provider| (provider, link.url)))
+    }
+
+    /// Short key of the post behind the url, the same for its original link and every mirror of it.
+    /// Unlike the position of the link, it doesn't change when other links of the text are edited.
+    pub async fn post_key(&self, url: &Url) -> Option<String> {
+        let provider = self.find_or_fallback(url).await?;
+        let post = provider.canonicalize(&self.clean(url));
+        Some(format!("{}_{:016x}", provider.id(), fnv1a(post.as_str())))
+    }
+
+    /// Find the first link in the text that points to the post with the [`post_key`](Self::post_key)
+    pub async fn find_post_link(&self, text: &str, key: &str) -> Option<Link> {
+        for link in links::find_links(text) {
+            if self.post_key(&link.url).await.as_deref() == Some(key) {
+                return Some(link);
+            }
+        }
+        None
+    }
+}
+
+/// FNV-1a hash, which unlike the hasher of the standard library stays the same across Rust versions
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self::with_builtin_providers(ThoriumClient::default(), &ThoriumConfig::default())
+    }
+}
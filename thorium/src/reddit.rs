@@ -0,0 +1,284 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde_json::Value;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::provider::{to_original, Mirror, Provider, ShortLinks};
+use crate::{
+    MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumConfig, ThoriumError,
+};
+
+pub const ID: &str = "reddit";
+
+pub const REDDIT_HOST: &str = "www.reddit.com";
+pub const REDDIT_SHORT_HOST: &str = "redd.it";
+
+/// Ids of the mirrors
+pub const RXDDIT: &str = "rxddit";
+pub const VXREDDIT: &str = "vxreddit";
+/// Id of the API, configured like mirrors
+pub const REDDIT_API: &str = "reddit_api";
+
+pub const RXDDIT_URL: &str = "https://rxddit.com/";
+pub const VXREDDIT_URL: &str = "https://vxreddit.com/";
+pub const REDDIT_API_URL: &str = "https://www.reddit.com/";
+
+static HOSTS: [&str; 7] = [
+    REDDIT_HOST,
+    "reddit.com",
+    "old.reddit.com",
+    "new.reddit.com",
+    "np.reddit.com",
+    "m.reddit.com",
+    REDDIT_SHORT_HOST,
+];
+
+pub struct Reddit {
+    mirrors: Vec<Mirror>,
+    api_url: Url,
+    short_links: ShortLinks,
+}
+
+impl Reddit {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![
+                Mirror::new(RXDDIT, RXDDIT_URL, config),
+                Mirror::new(VXREDDIT, VXREDDIT_URL, config),
+            ],
+            api_url: config.url(REDDIT_API, REDDIT_API_URL),
+            short_links: ShortLinks::default(),
+        }
+    }
+
+    /// `/r/<subreddit>/s/<code>`, the links of the share button
+    fn is_share_link(&self, url: &Url) -> bool {
+        matches!(path_segments(url)[..], ["r", _, "s", _])
+    }
+
+    /// Data of the post from the JSON API of Reddit
+    async fn fetch_post(&self, client: &ThoriumClient, url: &Url) -> Result<Value> {
+        let post_id = self
+            .post_id(&self.resolve(client, url).await?)
+            .ok_or_else(|| ThoriumError::UnsupportedUrl(url.to_string()))?;
+
+        let api_url = format!(
+            "{}/comments/{}.json?raw_json=1",
+            self.api_url.as_str().trim_end_matches('/'),
+            post_id
+        );
+        let listing: Value = serde_json::from_str(&client.get_text(&api_url).await?)?;
+
+        let post = &listing[0]["data"]["children"][0]["data"];
+        if !post.is_object() {
+            return Err(ThoriumError::Parse(format!("no post in {}", api_url)));
+        }
+        Ok(post.clone())
+    }
+}
+
+#[async_trait]
+impl Provider for Reddit {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// Posts come from the API of Reddit, never from a mirror
+    fn fetched_mirror(&self, _url: &Url) -> Option<&Mirror> {
+        None
+    }
+
+    /// `share_id` identifies who shared the link
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &["share_id", "ref", "ref_source"]
+    }
+
+    /// `/r/<subreddit>/comments/<id>`, `/comments/<id>`, `/user/<user>/comments/<id>`,
+    /// `/r/<subreddit>/s/<code>` share links or a `redd.it/<id>` short link
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            ["r" | "user" | "u", _, "comments", _, ..] | ["comments", _, ..] => true,
+            ["r", _, "s", _] => true,
+            [_] => has_host(url, &[REDDIT_SHORT_HOST]),
+            _ => false,
+        }
+    }
+
+    /// Short links are expanded, as mirrors only know the full ones
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = to_original(self, url);
+        if let [id] = path_segments(url)[..] {
+            if has_host(url, &[REDDIT_SHORT_HOST]) {
+                canonical.set_path(&format!("/comments/{}", id));
+            }
+        }
+        canonical
+    }
+
+    /// The base36 id of the post, unknown for share links
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            ["r" | "user" | "u", _, "comments", id, ..] | ["comments", id, ..] => {
+                Some(id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Share links only reveal the post they point to by redirecting to it
+    async fn resolve(&self, client: &ThoriumClient, url: &Url) -> Result<Url> {
+        let share_link = self.canonicalize(url);
+        if !self.is_share_link(&share_link) {
+            return Ok(url.clone());
+        }
+
+        self.short_links
+            .resolve(client, &share_link, |post| {
+                let mut post = self.canonicalize(post);
+                // The redirect carries who shared the link
+                post.set_query(None);
+                (has_host(&post, self.hosts()) && self.post_id(&post).is_some()).then_some(post)
+            })
+            .await
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let post = self.fetch_post(client, url).await?;
+        // The media of crossposts only lives in the original post
+        let original = match &post["crosspost_parent_list"][0] {
+            Value::Object(_) => &post["crosspost_parent_list"][0],
+            _ => &post,
+        };
+        Ok(MediaBundle::new(media_from_post(original)).non_empty())
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let post = post_info(&self.fetch_post(client, url).await?);
+        Ok((!post.is_empty()).then_some(post))
+    }
+}
+
+/// Images of galleries, the video of `v.redd.it` posts or a single linked image
+fn media_from_post(post: &Value) -> Vec<MediaItem> {
+    let preview = &post["preview"]["images"][0]["source"];
+
+    if post["is_gallery"].as_bool() == Some(true) {
+        let items = post["gallery_data"]["items"].as_array().cloned();
+        return items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|item| {
+                let mut media = gallery_item(&post["media_metadata"][item["media_id"].as_str()?])?;
+                media.alt_text = item["caption"].as_str().map(str::to_string);
+                Some(media)
+            })
+            .collect();
+    }
+
+    let video = [
+        &post["secure_media"]["reddit_video"],
+        &post["media"]["reddit_video"],
+        &post["preview"]["reddit_video_preview"],
+    ]
+    .into_iter()
+    .find(|video| video["fallback_url"].is_string());
+    if let Some(video) = video {
+        let kind = match video["is_gif"].as_bool() {
+            Some(true) => MediaKind::Gif,
+            _ => MediaKind::Video,
+        };
+        // The fallback is the highest quality stream that doesn't need DASH, it has no sound
+        let mut item = MediaItem::new(video["fallback_url"].as_str().unwrap_or_default(), kind);
+        item.width = dimension(&video["width"]);
+        item.height = dimension(&video["height"]);
+        item.duration = video["duration"].as_f64();
+        item.thumbnail = preview["url"].as_str().map(str::to_string);
+        return vec![item];
+    }
+
+    let link = post["url_overridden_by_dest"].as_str().unwrap_or_default();
+    if post["post_hint"].as_str() == Some("image") || has_image_extension(link) {
+        let mut item = MediaItem::from_url(link);
+        item.width = dimension(&preview["width"]);
+        item.height = dimension(&preview["height"]);
+        return vec![item];
+    }
+
+    Vec::new()
+}
+
+/// An entry of `media_metadata`, e.g. `{"e": "Image", "m": "image/jpg", "s": {"u": ..., "x": 1080, "y": 720}}`
+fn gallery_item(metadata: &Value) -> Option<MediaItem> {
+    if metadata["status"]
+        .as_str()
+        .is_some_and(|status| status != "valid")
+    {
+        return None;
+    }
+
+    let source = &metadata["s"];
+    let mut item = match metadata["e"].as_str()? {
+        "Image" => {
+            let mut item = MediaItem::new(source["u"].as_str()?, MediaKind::Image);
+            item.mime_type = metadata["m"].as_str().map(str::to_string);
+            item
+        }
+        // Animated images are served as mp4 when available, `m` is the type of the gif
+        "AnimatedImage" => MediaItem::new(
+            source["mp4"].as_str().or(source["gif"].as_str())?,
+            MediaKind::Gif,
+        ),
+        _ => return None,
+    };
+    item.width = dimension(&source["x"]);
+    item.height = dimension(&source["y"]);
+    Some(item)
+}
+
+fn post_info(post: &Value) -> PostInfo {
+    let string = |value: &Value| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let text = match (string(&post["title"]), string(&post["selftext"])) {
+        (Some(title), Some(body)) => Some(format!("{}\n\n{}", title, body)),
+        (title, body) => title.or(body),
+    };
+
+    PostInfo {
+        author_handle: string(&post["author"]),
+        text,
+        created_at: post["created_utc"]
+            .as_f64()
+            .and_then(|created| DateTime::from_timestamp(created as i64, 0)),
+        replies: post["num_comments"].as_u64(),
+        likes: post["score"].as_u64(),
+        quoted: post["crosspost_parent_list"][0]
+            .as_object()
+            .map(|_| Box::new(post_info(&post["crosspost_parent_list"][0]))),
+        ..Default::default()
+    }
+}
+
+fn dimension(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|value| u32::try_from(value).ok())
+}
+
+fn has_image_extension(link: &str) -> bool {
+    let path = link.split(['?', '#']).next().unwrap_or_default();
+    [".jpg", ".jpeg", ".png", ".webp", ".gif"]
+        .iter()
+        .any(|extension| path.to_ascii_lowercase().ends_with(extension))
+}
@@ -0,0 +1,69 @@
+use url::Url;
+
+use crate::links::path_segments;
+use crate::provider::{Mirror, Provider};
+use crate::ThoriumConfig;
+
+pub const ID: &str = "threads";
+
+pub const THREADS_HOST: &str = "www.threads.com";
+
+/// Ids of the mirrors
+pub const FIXTHREADS: &str = "fixthreads";
+pub const VXTHREADS: &str = "vxthreads";
+
+pub const FIXTHREADS_URL: &str = "https://fixthreads.net/";
+pub const VXTHREADS_URL: &str = "https://vxthreads.net/";
+
+static HOSTS: [&str; 4] = [
+    THREADS_HOST,
+    "threads.com",
+    "www.threads.net",
+    "threads.net",
+];
+
+pub struct Threads {
+    mirrors: Vec<Mirror>,
+}
+
+impl Threads {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![
+                Mirror::new(FIXTHREADS, FIXTHREADS_URL, config),
+                Mirror::new(VXTHREADS, VXTHREADS_URL, config),
+            ],
+        }
+    }
+}
+
+impl Provider for Threads {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// `/@<user>/post/<code>`, optionally followed by `/media`, or a `/t/<code>` short link
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            [user, "post", _, ..] => user.len() > 1 && user.starts_with('@'),
+            ["t", _] => true,
+            _ => false,
+        }
+    }
+
+    /// The shortcode, which short links share with the full ones
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            [_, "post", code, ..] | ["t", code] => Some(code.to_string()),
+            _ => None,
+        }
+    }
+}
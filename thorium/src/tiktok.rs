@@ -1,72 +1,133 @@
-pub const TIKTOK_URL: &str = "https://www.tiktok.com/";
-pub const TIKTOK_URL_TK: &str = "https://vt.tiktok.com/";
-pub const TIKTOK_NWWW_URL: &str = "https://tiktok.com/";
-pub const TNKTOK_URL: &str = "https://tnktok.com/";
+use async_trait::async_trait;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::provider::{to_original, Mirror, Provider, ShortLinks};
+use crate::{MediaBundle, PostInfo, Result, ThoriumClient, ThoriumConfig};
+
+pub const ID: &str = "tiktok";
+
+pub const TIKTOK_HOST: &str = "www.tiktok.com";
+pub const TIKTOK_SHORT_HOST: &str = "vt.tiktok.com";
+pub const TIKTOK_SHORT_HOST_ALT: &str = "vm.tiktok.com";
+
+/// Ids of the mirrors
+pub const TIKTXK: &str = "tiktxk";
+pub const TNKTOK: &str = "tnktok";
+
 pub const TIKTXK_URL: &str = "https://tiktxk.com/";
+pub const TNKTOK_URL: &str = "https://tnktok.com/";
 
-#[derive(Debug, PartialEq)]
-pub enum UrlType {
-    TikTok,
-    TNKTOK,
-    TIKTXK,
-    Unknown
+static HOSTS: [&str; 5] = [
+    TIKTOK_HOST,
+    "tiktok.com",
+    "m.tiktok.com",
+    TIKTOK_SHORT_HOST,
+    TIKTOK_SHORT_HOST_ALT,
+];
+
+pub struct TikTok {
+    mirrors: Vec<Mirror>,
+    short_links: ShortLinks,
 }
 
-impl UrlType {
-    pub fn as_str(&self) -> &'static str {
-        match *self {
-            UrlType::TikTok => TIKTOK_URL,
-            UrlType::TIKTXK => TIKTXK_URL,
-            UrlType::TNKTOK => TNKTOK_URL,
-            UrlType::Unknown => "",
+impl TikTok {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![
+                Mirror::new(TIKTXK, TIKTXK_URL, config),
+                Mirror::new(TNKTOK, TNKTOK_URL, config),
+            ],
+            short_links: ShortLinks::default(),
         }
     }
 
-    pub fn from_string(url: &str) -> UrlType {
-        if url.contains(TIKTOK_URL) || url.contains(TIKTOK_NWWW_URL) || url.contains(TIKTOK_URL_TK) {
-            return UrlType::TikTok;
-        } else if url.contains(TNKTOK_URL) {
-            return UrlType::TNKTOK;
-        } else if url.contains(TIKTXK_URL) {
-            return UrlType::TIKTXK;
+    /// `vt.tiktok.com/<code>`, `vm.tiktok.com/<code>` or `tiktok.com/t/<code>`
+    fn is_short_link(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            [_] => has_host(url, &[TIKTOK_SHORT_HOST, TIKTOK_SHORT_HOST_ALT]),
+            ["t", _] => !has_host(url, &[TIKTOK_SHORT_HOST, TIKTOK_SHORT_HOST_ALT]),
+            _ => false,
         }
-        UrlType::Unknown
     }
 }
 
-pub fn is_tiktok_url(url: &str) -> bool {
-    UrlType::from_string(url) == UrlType::TikTok
-}
+#[async_trait]
+impl Provider for TikTok {
+    fn id(&self) -> &'static str {
+        ID
+    }
 
-pub async fn convert_url(url: String, from: UrlType, to: UrlType) -> String {
-    clear_url(url).await.replace(from.as_str(), to.as_str())
-}
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
 
-pub async fn convert_url_lazy(url: String, to: UrlType) -> String {
-    let cleared_url = clear_url(url.clone()).await;
-    let from = UrlType::from_string(&cleared_url);
-    url.replace(from.as_str(), to.as_str())
-}
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
 
-/**
- * Remove vt. from the URL to get the original URL
- */
-pub async fn clear_url(url: String) -> String {
-    if url.contains("vt.") {
-        // Remove vt. from the URL
-        return url.replace("vt.", "");
-    } else if !url.contains("www.") {
-        // Append www. to the URL
-        return url.replace("tiktok.com", "www.tiktok.com");
+    /// Shared links carry the device and account that shared them
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &["_r", "_t", "is_from_webapp", "sender_device", "sender_web_id", "share_*"]
     }
-    url
-}
 
-pub async fn get_url_type(url: String) -> UrlType {
-    UrlType::from_string(&url)
-}
+    /// `/@<user>/video/<id>` or a short link
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            [user, "video", _, ..] => user.starts_with('@'),
+            _ => self.is_short_link(url),
+        }
+    }
+
+    /// Short links keep their host, as only it knows where they point.
+    /// Videos lose the share tracking TikTok appends to them.
+    fn canonicalize(&self, url: &Url) -> Url {
+        if self.is_short_link(url) && has_host(url, self.hosts()) {
+            let mut canonical = url.clone();
+            let _ = canonical.set_scheme("https");
+            canonical.set_query(None);
+            return canonical;
+        }
+
+        let mut canonical = to_original(self, url);
+        if matches!(path_segments(&canonical)[..], [_, "video", _, ..]) {
+            canonical.set_query(None);
+            canonical.set_fragment(None);
+        }
+        canonical
+    }
+
+    /// The numeric id of the video, or the code of a short link
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            [user, "video", id, ..] if user.starts_with('@') => Some(id.to_string()),
+            [.., code] if self.is_short_link(url) => Some(code.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Follow the redirects of short links to the video they point to
+    async fn resolve(&self, client: &ThoriumClient, url: &Url) -> Result<Url> {
+        let short_link = self.canonicalize(url);
+        if !self.is_short_link(&short_link) {
+            return Ok(url.clone());
+        }
+
+        self.short_links
+            .resolve(client, &short_link, |video| {
+                let is_video = has_host(video, self.hosts()) && !self.is_short_link(video);
+                (is_video && self.is_post(video)).then(|| self.canonicalize(video))
+            })
+            .await
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let video = self.resolve(client, url).await?;
+        crate::get_media(client, self.media_url(&video).to_string()).await
+    }
 
-pub async fn get_media_from_url(mut url: String) -> String {
-    url = convert_url_lazy(url, UrlType::TIKTXK).await;
-    crate::get_media(url).await
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let video = self.resolve(client, url).await?;
+        crate::get_post(client, self.media_url(&video).to_string()).await
+    }
 }
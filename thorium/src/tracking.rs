@@ -0,0 +1,43 @@
+use url::{form_urlencoded, Url};
+
+/// Query parameters that only track who shared a link, on every site.
+/// A trailing `*` matches any parameter starting with what precedes it.
+pub const TRACKING_PARAMETERS: [&str; 4] = ["utm_*", "fbclid", "gclid", "mc_eid"];
+
+/// Whether the query parameter matches one of the rules
+fn matches(parameter: &str, rules: &[&str]) -> bool {
+    rules.iter().any(|rule| match rule.strip_suffix('*') {
+        Some(prefix) => parameter.starts_with(prefix),
+        None => parameter == *rule,
+    })
+}
+
+/// Copy of the url without the tracking parameters of every site nor those in `rules`.
+/// Every other parameter is kept as it was, in its original order.
+pub fn remove_tracking(url: &Url, rules: &[&str]) -> Url {
+    let mut cleaned = url.clone();
+    let query = match url.query() {
+        Some(query) => query,
+        None => return cleaned,
+    };
+
+    // The raw pairs are kept so that values aren't re-encoded
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            let key = form_urlencoded::parse(key.as_bytes())
+                .next()
+                .map(|(key, _)| key.into_owned())
+                .unwrap_or_default();
+            !pair.is_empty() && !matches(&key, &TRACKING_PARAMETERS) && !matches(&key, rules)
+        })
+        .collect();
+
+    if kept.is_empty() {
+        cleaned.set_query(None);
+    } else {
+        cleaned.set_query(Some(&kept.join("&")));
+    }
+    cleaned
+}
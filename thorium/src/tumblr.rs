@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde_json::Value;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::post::html_to_text;
+use crate::provider::{to_original, Mirror, Provider};
+use crate::{
+    MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumConfig, ThoriumError,
+};
+
+pub const ID: &str = "tumblr";
+
+pub const TUMBLR_HOST: &str = "www.tumblr.com";
+/// Blogs also live on subdomains, e.g. `staff.tumblr.com`
+pub const BLOG_HOST_SUFFIX: &str = ".tumblr.com";
+
+/// Id of the mirror, there is no public default so it only exists when configured
+pub const FXTUMBLR: &str = "fxtumblr";
+
+static HOSTS: [&str; 2] = [TUMBLR_HOST, "tumblr.com"];
+
+/// Subdomains of Tumblr that aren't blogs
+static RESERVED_SUBDOMAINS: [&str; 3] = ["www", "api", "assets"];
+/// Pages of tumblr.com that aren't blogs
+static RESERVED_PATHS: [&str; 6] = ["post", "tagged", "search", "explore", "dashboard", "likes"];
+
+pub struct Tumblr {
+    mirrors: Vec<Mirror>,
+}
+
+impl Tumblr {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: Mirror::configured(FXTUMBLR, config).into_iter().collect(),
+        }
+    }
+
+    /// Post as returned by the legacy read API of the blog, which needs no API key
+    async fn fetch_post(&self, client: &ThoriumClient, url: &Url) -> Result<Value> {
+        let canonical = self.canonicalize(url);
+        let (blog, id) = match path_segments(&canonical)[..] {
+            [blog, id, ..] => (blog.to_string(), id.to_string()),
+            _ => return Err(ThoriumError::UnsupportedUrl(url.to_string())),
+        };
+
+        let api_url = format!(
+            "https://{}{}/api/read/json?id={}",
+            blog, BLOG_HOST_SUFFIX, id
+        );
+        let content = client.get_text(&api_url).await?;
+        // The JSON is wrapped in a script assigning it to a variable
+        let json = content
+            .trim()
+            .trim_start_matches("var tumblr_api_read = ")
+            .trim_end_matches(';');
+        let read: Value = serde_json::from_str(json)?;
+
+        let post = &read["posts"][0];
+        if !post.is_object() {
+            return Err(ThoriumError::Parse(format!("no post in {}", api_url)));
+        }
+        let mut post = post.clone();
+        post["tumblelog"] = read["tumblelog"].clone();
+        Ok(post)
+    }
+}
+
+/// Name of the blog if the url is on its subdomain
+fn blog_subdomain(url: &Url) -> Option<&str> {
+    let blog = url
+        .host_str()?
+        .strip_suffix(BLOG_HOST_SUFFIX)
+        .filter(|blog| !blog.contains('.'))?;
+    (!RESERVED_SUBDOMAINS.contains(&blog)).then_some(blog)
+}
+
+fn is_post_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+#[async_trait]
+impl Provider for Tumblr {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// Posts come from the API of the blog, never from a mirror
+    fn fetched_mirror(&self, _url: &Url) -> Option<&Mirror> {
+        None
+    }
+
+    /// `/post/<id>` on the subdomain of a blog, `/<blog>/<id>` or `/blog/view/<blog>/<id>` on tumblr.com
+    fn is_post(&self, url: &Url) -> bool {
+        if blog_subdomain(url).is_some() {
+            return matches!(path_segments(url)[..], ["post", id, ..] if is_post_id(id));
+        }
+        match path_segments(url)[..] {
+            ["blog", "view", _, id, ..] => is_post_id(id),
+            [blog, id, ..] => !RESERVED_PATHS.contains(&blog) && is_post_id(id),
+            _ => false,
+        }
+    }
+
+    fn detect(&self, url: &Url) -> bool {
+        (has_host(url, self.hosts()) || blog_subdomain(url).is_some()) && self.is_post(url)
+    }
+
+    fn owns(&self, url: &Url) -> bool {
+        let on_mirror = self.mirrors().iter().any(|mirror| mirror.serves(url));
+        self.detect(url) || (on_mirror && self.is_post(&self.canonicalize(url)))
+    }
+
+    /// Both styles become `tumblr.com/<blog>/<id>`, optionally followed by the slug
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = to_original(self, url);
+        let path = match (blog_subdomain(url), &path_segments(url)[..]) {
+            (Some(blog), ["post", rest @ ..]) => Some(format!("/{}/{}", blog, rest.join("/"))),
+            (None, ["blog", "view", rest @ ..]) => Some(format!("/{}", rest.join("/"))),
+            _ => None,
+        };
+        if let Some(path) = path {
+            canonical.set_path(&path);
+        }
+        canonical
+    }
+
+    /// The blog and the id of the post, ids are only unique per blog
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            [blog, id, ..] if self.is_post(url) => Some(format!("{}/{}", blog, id)),
+            _ => None,
+        }
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let post = self.fetch_post(client, url).await?;
+        Ok(MediaBundle::new(media_from_post(&post)).non_empty())
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let post = post_info(&self.fetch_post(client, url).await?);
+        Ok((!post.is_empty()).then_some(post))
+    }
+}
+
+/// Photos of photosets, the video of video posts and media embedded in the text of other posts.
+/// Reblogs carry the media of the post they reblog.
+fn media_from_post(post: &Value) -> Vec<MediaItem> {
+    let mut items = Vec::new();
+
+    let photos = post["photos"].as_array().cloned().unwrap_or_default();
+    if photos.is_empty() {
+        items.extend(photo_item(post));
+    }
+    items.extend(photos.iter().filter_map(photo_item));
+
+    for field in ["video-player", "regular-body", "answer", "photo-caption"] {
+        if let Some(html) = post[field].as_str() {
+            items.extend(media_from_html(html));
+        }
+    }
+
+    items.dedup_by(|a, b| a.url == b.url);
+    items
+}
+
+/// A photo of a photoset, or the single photo of a photo post
+fn photo_item(photo: &Value) -> Option<MediaItem> {
+    let mut item = MediaItem::from_url(photo["photo-url-1280"].as_str()?);
+    item.width = dimension(&photo["width"]);
+    item.height = dimension(&photo["height"]);
+    item.alt_text = photo["caption"]
+        .as_str()
+        .filter(|caption| !caption.is_empty())
+        .map(str::to_string);
+    Some(item)
+}
+
+/// Images and videos of posts written with the block editor, which end up as HTML
+fn media_from_html(html: &str) -> Vec<MediaItem> {
+    let html = scraper::Html::parse_fragment(html);
+    let selector = scraper::Selector::parse("img[src], video[src], video source[src]").unwrap();
+
+    html.select(&selector)
+        .filter_map(|element| {
+            let element = element.value();
+            let url = element.attr("src")?;
+            let mut item = match element.name() {
+                "img" => MediaItem::new(url, MediaKind::Image),
+                _ => MediaItem::new(url, MediaKind::Video),
+            };
+            item.mime_type = element.attr("type").map(str::to_string);
+            item.alt_text = element.attr("alt").map(str::to_string);
+            Some(item)
+        })
+        // Images hosted elsewhere are emoji, avatars or tracking pixels rather than media
+        .filter(|item| item.kind == MediaKind::Video || item.url.contains(".media.tumblr.com"))
+        .collect()
+}
+
+fn post_info(post: &Value) -> PostInfo {
+    let string = |value: &Value| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let text = [
+        "regular-title",
+        "regular-body",
+        "photo-caption",
+        "video-caption",
+        "question",
+        "answer",
+    ]
+    .iter()
+    .filter_map(|field| string(&post[*field]))
+    .map(|html| html_to_text(&html))
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>();
+
+    PostInfo {
+        author_name: string(&post["tumblelog"]["title"]),
+        author_handle: string(&post["tumblelog"]["name"]),
+        text: (!text.is_empty()).then(|| text.join("\n\n")),
+        created_at: post["unix-timestamp"]
+            .as_i64()
+            .and_then(|created| DateTime::from_timestamp(created, 0)),
+        ..Default::default()
+    }
+}
+
+fn dimension(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|value| u32::try_from(value).ok())
+}
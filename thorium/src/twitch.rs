@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::provider::{Mirror, Provider};
+use crate::{MediaBundle, MediaItem, MediaKind, Result, ThoriumClient};
+
+pub const ID: &str = "twitch";
+
+pub const CLIPS_HOST: &str = "clips.twitch.tv";
+
+static HOSTS: [&str; 4] = [CLIPS_HOST, "www.twitch.tv", "twitch.tv", "m.twitch.tv"];
+
+/// Clips of Twitch, which embed fine but can't be downloaded from the page
+#[derive(Default)]
+pub struct Twitch;
+
+impl Twitch {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Provider for Twitch {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &[]
+    }
+
+    /// `clips.twitch.tv/<slug>` or `twitch.tv/<channel>/clip/<slug>`
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            [slug] => has_host(url, &[CLIPS_HOST]) && slug != "embed",
+            [_, "clip", _] => !has_host(url, &[CLIPS_HOST]),
+            _ => false,
+        }
+    }
+
+    /// Both styles become `clips.twitch.tv/<slug>`
+    fn canonicalize(&self, url: &Url) -> Url {
+        let slug = path_segments(url).last().copied().unwrap_or_default();
+        let mut canonical = Url::parse(&format!("https://{}/", CLIPS_HOST)).unwrap();
+        canonical.set_path(slug);
+        canonical
+    }
+
+    /// The slug of the clip
+    fn post_id(&self, url: &Url) -> Option<String> {
+        match path_segments(&self.canonicalize(url))[..] {
+            [slug] if self.is_post(url) => Some(slug.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The MP4 of the clip, for clips whose thumbnail still reveals where it is stored
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        let page = crate::get_media(client, self.canonicalize(url).to_string()).await?;
+        let items = page
+            .map(|page| page.items)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|thumbnail| {
+                let mut item =
+                    MediaItem::new(clip_from_thumbnail(&thumbnail.url)?, MediaKind::Video);
+                item.width = thumbnail.width;
+                item.height = thumbnail.height;
+                item.thumbnail = Some(thumbnail.url);
+                Some(item)
+            })
+            .collect();
+        Ok(MediaBundle::new(items).non_empty())
+    }
+}
+
+/// `<clip>-preview-480x272.jpg` thumbnails are stored next to `<clip>.mp4`
+fn clip_from_thumbnail(thumbnail: &str) -> Option<String> {
+    let (clip, size) = thumbnail.rsplit_once("-preview-")?;
+    let (width, height) = size.strip_suffix(".jpg")?.split_once('x')?;
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    (is_number(width) && is_number(height)).then(|| format!("{}.mp4", clip))
+}
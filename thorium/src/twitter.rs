@@ -1,166 +1,322 @@
+use async_trait::async_trait;
+use chrono::DateTime;
 use serde::Deserialize;
-use tokio::task::spawn_blocking;
+use url::Url;
 
-use crate::USER_AGENT;
+use crate::links::{has_host, path_segments};
+use crate::provider::{to_original, with_base, with_host, Mirror, Provider};
+use crate::{
+    scrape_media, MediaBundle, MediaItem, MediaKind, PostInfo, Result, ThoriumClient, ThoriumConfig,
+};
+
+pub const ID: &str = "twitter";
+
+pub const TWITTER_HOST: &str = "twitter.com";
+pub const X_HOST: &str = "x.com";
+
+/// Ids of the mirrors
+pub const VXTWITTER: &str = "vxtwitter";
+pub const FXTWITTER: &str = "fxtwitter";
+/// Ids of the APIs, configured like mirrors
+pub const VXTWITTER_API: &str = "vxtwitter_api";
+pub const MOSAIC: &str = "mosaic";
 
-pub const TWITTER_URL: &str = "https://twitter.com/";
-pub const X_URL: &str = "https://x.com/";
-pub const FXTWITTER_URL: &str = "https://fxtwitter.com/";
 pub const VXTWITTER_URL: &str = "https://vxtwitter.com/";
-const MOSAIC_URL: &str = "https://mosaic.fxtwitter.com/";
-const VXTWITTER_COMBINER_URL: &str = "https://vxtwitter.com/rendercombined.jpg?imgs=";
-const VXTWITTER_API_URL: &str = "https://api.vxtwitter.com/";
-
-#[derive(Debug, PartialEq)]
-pub enum UrlType {
-    Twitter,
-    X,
-    Fxtwitter,
-    Vxtwitter,
-    Mosaic,
-    Unknown,
+pub const FXTWITTER_URL: &str = "https://fxtwitter.com/";
+pub const VXTWITTER_API_URL: &str = "https://api.vxtwitter.com/";
+pub const MOSAIC_URL: &str = "https://mosaic.fxtwitter.com/";
+
+static HOSTS: [&str; 6] = [
+    TWITTER_HOST,
+    X_HOST,
+    "www.twitter.com",
+    "www.x.com",
+    "mobile.twitter.com",
+    "mobile.x.com",
+];
+
+/// Other frontends of Twitter, links to them stand for the same tweets as the original site
+static ALIASES: [&str; 8] = [
+    "fixupx.com",
+    "fixvx.com",
+    "girlcockx.com",
+    "stupidpenisx.com",
+    "twittpr.com",
+    "twstalker.com",
+    "xcancel.com",
+    "nitter.net",
+];
+
+/// Subdomains of frontends that serve the media of the tweet directly
+static DIRECT_SUBDOMAINS: [&str; 2] = ["d.", "g."];
+
+/// Extensions frontends accept after the id to serve the media directly, e.g. `/status/20.jpg`
+static DIRECT_EXTENSIONS: [&str; 4] = [".jpg", ".png", ".mp4", ".gif"];
+
+pub struct Twitter {
+    mirrors: Vec<Mirror>,
+    api_url: Url,
+    mosaic_url: Url,
 }
 
-impl UrlType {
-    pub fn as_str(&self) -> &'static str {
-        match *self {
-            UrlType::Twitter => TWITTER_URL,
-            UrlType::X => X_URL,
-            UrlType::Fxtwitter => FXTWITTER_URL,
-            UrlType::Vxtwitter => VXTWITTER_URL,
-            UrlType::Mosaic => MOSAIC_URL,
-            UrlType::Unknown => "",
+impl Twitter {
+    pub fn new(config: &ThoriumConfig) -> Self {
+        Self {
+            mirrors: vec![
+                Mirror::new(VXTWITTER, VXTWITTER_URL, config),
+                Mirror::new(FXTWITTER, FXTWITTER_URL, config),
+            ],
+            api_url: config.url(VXTWITTER_API, VXTWITTER_API_URL),
+            mosaic_url: config.url(MOSAIC, MOSAIC_URL),
         }
     }
 
-    pub fn from_string(url: &str) -> UrlType {
-        if url.contains(TWITTER_URL) {
-            return UrlType::Twitter;
-        } else if url.contains(X_URL) {
-            return UrlType::X;
-        } else if url.contains(FXTWITTER_URL) {
-            return UrlType::Fxtwitter;
-        } else if url.contains(VXTWITTER_URL) {
-            return UrlType::Vxtwitter;
-        } else if url.contains(MOSAIC_URL) {
-            return UrlType::Mosaic;
+    /// Whether the url points to a frontend that isn't one of the mirrors, e.g. fixupx or a nitter instance
+    fn is_alias(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        if ALIASES.contains(&host) || host.starts_with("nitter.") {
+            return true;
         }
-        UrlType::Unknown
+
+        // Direct media subdomains of the mirrors and of the other frontends
+        DIRECT_SUBDOMAINS
+            .iter()
+            .filter_map(|subdomain| host.strip_prefix(subdomain))
+            .any(|frontend| {
+                ALIASES.contains(&frontend)
+                    || self
+                        .mirrors
+                        .iter()
+                        .any(|mirror| mirror.base_url.host_str() == Some(frontend))
+            })
     }
-}
 
-pub fn remove_tracking(url: String) -> String {
-    let mut new_url = String::new();
-    for word in url.split_whitespace() {
-        if word.contains('?') && word.contains('=') {
-            let url: &str;
-            if !word.starts_with("https://") && word.contains("https://") {
-                let split = word.split_once("https://").unwrap();
-                url = split.1;
-                new_url.push_str(split.0);
-                new_url.push(' ');
-                new_url.push_str("https://");
-            } else {
-                url = word.split_once('?').unwrap().0;
+    async fn get_media_from_fxtwitter(
+        &self,
+        client: &ThoriumClient,
+        url: String,
+    ) -> Result<Option<MediaBundle>> {
+        let content = client.get_text(&url).await?;
+
+        let mut bundle = scrape_media(content, "twitter:image").await?;
+        if let Some(bundle) = &mut bundle {
+            // Posts with multiple images only link to a mosaic of them
+            for item in &mut bundle.items {
+                if item.url.starts_with(self.mosaic_url.as_str()) {
+                    item.url.push_str(".jpg");
+                    bundle.mosaic = Some(item.url.clone());
+                }
             }
-            new_url.push_str(url);
+        }
+        Ok(bundle)
+    }
+
+    async fn fetch_vxtwitter(&self, client: &ThoriumClient, url: &Url) -> Result<APIFXResponse> {
+        let url = with_base(&self.canonicalize(url), &self.api_url);
+        let content = client.get_text(url.as_str()).await?;
+        Ok(serde_json::from_str::<APIFXResponse>(&content)?)
+    }
+
+    async fn get_media_from_vxtwitter(
+        &self,
+        client: &ThoriumClient,
+        url: &Url,
+    ) -> Result<Option<MediaBundle>> {
+        let json = self.fetch_vxtwitter(client, url).await?;
+
+        let items: Vec<MediaItem> = if json.media_extended.is_empty() {
+            json.media_urls
+                .into_iter()
+                .map(MediaItem::from_url)
+                .collect()
         } else {
-            new_url.push_str(word);
+            json.media_extended
+                .into_iter()
+                .map(MediaItem::from)
+                .collect()
+        };
+
+        let mut bundle = MediaBundle::new(items);
+        let only_images = bundle
+            .items
+            .iter()
+            .all(|item| item.kind == MediaKind::Image);
+        // VXTwitter combines the images itself
+        if let Some(vxtwitter) = self
+            .mirror(VXTWITTER)
+            .filter(|_| bundle.items.len() > 1 && only_images)
+        {
+            bundle.mosaic = Some(format!(
+                "{}/rendercombined.jpg?imgs={}",
+                vxtwitter.base_url.as_str().trim_end_matches('/'),
+                bundle.urls().collect::<Vec<_>>().join(",")
+            ));
         }
-        new_url.push(' ');
+        Ok(bundle.non_empty())
     }
-    new_url.trim().to_string()
 }
 
-pub async fn convert_url(url: String, from: UrlType, to: UrlType) -> String {
-    url.replace(from.as_str(), to.as_str())
-}
+#[async_trait]
+impl Provider for Twitter {
+    fn id(&self) -> &'static str {
+        ID
+    }
 
-pub async fn convert_url_lazy(url: String, to: UrlType) -> String {
-    let from = UrlType::from_string(&url);
-    url.replace(from.as_str(), to.as_str())
-}
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
 
-pub async fn get_url_type(url: String) -> UrlType {
-    UrlType::from_string(&url)
-}
+    fn mirrors(&self) -> &[Mirror] {
+        &self.mirrors
+    }
+
+    /// Share sheets add `s` and `t`, embeds `ref_src` and `ref_url`
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &["s", "t", "ref_src", "ref_url"]
+    }
+
+    /// `/<user>/status/<id>`, `/i/status/<id>` and `/i/web/status/<id>`
+    fn is_post(&self, url: &Url) -> bool {
+        match path_segments(url)[..] {
+            [_, "status", id, ..] | ["i", "web", "status", id, ..] => {
+                !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit())
+            }
+            _ => false,
+        }
+    }
+
+    fn detect(&self, url: &Url) -> bool {
+        (has_host(url, self.hosts()) || self.is_alias(url)) && self.is_post(&self.canonicalize(url))
+    }
+
+    fn owns(&self, url: &Url) -> bool {
+        let on_mirror = self.mirrors.iter().any(|mirror| mirror.serves(url));
+        (on_mirror && self.is_post(&self.canonicalize(url))) || self.detect(url)
+    }
 
-pub fn is_twitter_url(url: &str) -> bool {
-    url.contains(TWITTER_URL) || url.contains(X_URL)
+    /// Every frontend goes back to Twitter, direct media links back to their tweet
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = if self.is_alias(url) {
+            let mut canonical = with_host(url, TWITTER_HOST);
+            // Nitter marks the tweet a page is about with `#m`
+            canonical.set_fragment(None);
+            canonical
+        } else {
+            to_original(self, url)
+        };
+
+        let direct = match path_segments(&canonical)[..] {
+            [user, "status", id] => DIRECT_EXTENSIONS
+                .iter()
+                .find_map(|extension| id.strip_suffix(extension))
+                .map(|id| format!("/{}/status/{}", user, id)),
+            _ => None,
+        };
+        if let Some(path) = direct {
+            canonical.set_path(&path);
+        }
+        canonical
+    }
+
+    /// The numeric id of the tweet
+    fn post_id(&self, url: &Url) -> Option<String> {
+        let canonical = self.canonicalize(url);
+        if !self.is_post(&canonical) {
+            return None;
+        }
+        match path_segments(&canonical)[..] {
+            [_, "status", id, ..] | ["i", "web", "status", id, ..] => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    async fn get_media(&self, client: &ThoriumClient, url: &Url) -> Result<Option<MediaBundle>> {
+        // FXTwitter is scraped, everything else goes through the VXTwitter API
+        match self.mirror(FXTWITTER) {
+            Some(fxtwitter) if fxtwitter.serves(url) => {
+                let url = self.rewrite(url, fxtwitter);
+                self.get_media_from_fxtwitter(client, url.to_string()).await
+            }
+            _ => self.get_media_from_vxtwitter(client, url).await,
+        }
+    }
+
+    async fn get_post(&self, client: &ThoriumClient, url: &Url) -> Result<Option<PostInfo>> {
+        let post = PostInfo::from(self.fetch_vxtwitter(client, url).await?);
+        Ok((!post.is_empty()).then_some(post))
+    }
 }
 
 #[derive(Deserialize)]
 struct APIFXResponse {
-    #[serde(rename = "mediaURLs")]
+    #[serde(rename = "mediaURLs", default)]
     media_urls: Vec<String>,
+    #[serde(default)]
+    media_extended: Vec<APIMedia>,
+    text: Option<String>,
+    user_name: Option<String>,
+    user_screen_name: Option<String>,
+    date_epoch: Option<i64>,
+    replies: Option<u64>,
+    retweets: Option<u64>,
+    likes: Option<u64>,
+    lang: Option<String>,
+    /// The quoted tweet
+    qrt: Option<Box<APIFXResponse>>,
 }
 
-pub async fn get_media_from_url(mut url: String) -> String {
-    let source = UrlType::from_string(&url);
-
-    if source == UrlType::Vxtwitter {
-        url = url.replace(VXTWITTER_URL, VXTWITTER_API_URL);
-    }
-
-    // We don't want to follow the redirect so we can get the metadata
-    let client = reqwest::Client::builder()
-        .user_agent(USER_AGENT)
-        .build()
-        .unwrap();
-
-    let request = client
-        .get(&url)
-        .header("user-agent", USER_AGENT)
-        .send()
-        .await
-        .unwrap();
-
-    if source == UrlType::Fxtwitter {
-        let content = request.text().await.unwrap();
-
-        // Check if content has a meta property and return it in a blocking thread
-        url = spawn_blocking(move || {
-            let selector_img = scraper::Selector::parse("meta[property='twitter:image']").unwrap();
-            let selector_video = scraper::Selector::parse("meta[property='og:video']").unwrap();
-            let html = scraper::Html::parse_document(content.as_str());
-            let vid = html.select(&selector_video).next();
-            let img = html.select(&selector_img).next();
-            if vid.is_none() && img.is_none() {
-                return "0".to_string();
-            }
-
-            let url = if let Some(vid) = vid {
-                vid
-            } else {
-                img.unwrap()
-            };
+impl From<APIFXResponse> for PostInfo {
+    fn from(tweet: APIFXResponse) -> Self {
+        PostInfo {
+            author_name: tweet.user_name,
+            author_handle: tweet.user_screen_name,
+            text: tweet.text.filter(|text| !text.is_empty()),
+            created_at: tweet
+                .date_epoch
+                .and_then(|epoch| DateTime::from_timestamp(epoch, 0)),
+            replies: tweet.replies,
+            reposts: tweet.retweets,
+            likes: tweet.likes,
+            quoted: tweet.qrt.map(|quoted| Box::new(PostInfo::from(*quoted))),
+            language: tweet.lang,
+        }
+    }
+}
 
-            url.value().attr("content").unwrap().to_string()
-        })
-        .await
-        .unwrap();
+#[derive(Deserialize)]
+struct APIMedia {
+    url: String,
+    #[serde(rename = "type")]
+    kind: String,
+    size: Option<APISize>,
+    thumbnail_url: Option<String>,
+    #[serde(rename = "altText")]
+    alt_text: Option<String>,
+    duration_millis: Option<u64>,
+}
 
-        if url.contains(MOSAIC_URL) {
-            url.push_str(".jpg")
-        }
-    } else if source == UrlType::Vxtwitter {
-        let json = request.json::<APIFXResponse>().await.unwrap();
+#[derive(Deserialize)]
+struct APISize {
+    width: u32,
+    height: u32,
+}
 
-        if json.media_urls.len() == 1
-            || json.media_urls.iter().any(|item| {
-                item.contains(".mp4") || item.contains(".webm") || item.contains(".gif")
-            })
-        {
-            return json.media_urls[0].clone();
-        }
+impl From<APIMedia> for MediaItem {
+    fn from(media: APIMedia) -> Self {
+        let kind = match media.kind.as_str() {
+            "video" => MediaKind::Video,
+            "gif" => MediaKind::Gif,
+            "image" => MediaKind::Image,
+            _ => MediaKind::from_url(&media.url),
+        };
 
-        // add all in vector to url
-        url = VXTWITTER_COMBINER_URL.to_string();
-        for media in json.media_urls {
-            url.push_str(media.as_str());
-            url.push(',');
-        }
-        url.pop();
+        let mut item = MediaItem::new(media.url, kind);
+        item.width = media.size.as_ref().map(|size| size.width);
+        item.height = media.size.as_ref().map(|size| size.height);
+        item.duration = media.duration_millis.map(|millis| millis as f64 / 1000.0);
+        item.thumbnail = media.thumbnail_url;
+        item.alt_text = media.alt_text.filter(|alt| !alt.is_empty());
+        item
     }
-    url
 }
@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use url::Url;
+
+use crate::links::{has_host, path_segments};
+use crate::provider::{Mirror, Provider};
+use crate::{MediaBundle, Result, ThoriumClient, ThoriumError};
+
+pub const ID: &str = "youtube";
+
+pub const YOUTUBE_HOST: &str = "www.youtube.com";
+pub const YOUTUBE_SHORT_HOST: &str = "youtu.be";
+
+static HOSTS: [&str; 4] = [
+    YOUTUBE_HOST,
+    "youtube.com",
+    "m.youtube.com",
+    YOUTUBE_SHORT_HOST,
+];
+
+/// Shorts and short links, which are normalized to `watch?v=` links as those embed properly
+#[derive(Default)]
+pub struct YouTube;
+
+impl YouTube {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn video_id(&self, url: &Url) -> Option<String> {
+        if !has_host(url, self.hosts()) {
+            return None;
+        }
+        let id = match path_segments(url)[..] {
+            ["shorts", id] => id.to_string(),
+            [id] if has_host(url, &[YOUTUBE_SHORT_HOST]) => id.to_string(),
+            ["watch"] => url
+                .query_pairs()
+                .find(|(key, _)| key == "v")
+                .map(|(_, id)| id.to_string())?,
+            _ => return None,
+        };
+        let is_id = id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+        (!id.is_empty() && is_id).then_some(id)
+    }
+}
+
+#[async_trait]
+impl Provider for YouTube {
+    fn id(&self) -> &'static str {
+        ID
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &HOSTS
+    }
+
+    fn mirrors(&self) -> &[Mirror] {
+        &[]
+    }
+
+    /// `si` identifies who shared the link, `t` is kept as it is the timestamp
+    fn tracking_parameters(&self) -> &'static [&'static str] {
+        &["si", "feature", "pp"]
+    }
+
+    /// `/shorts/<id>`, `youtu.be/<id>` or the `/watch?v=<id>` they are normalized to
+    fn is_post(&self, url: &Url) -> bool {
+        self.video_id(url).is_some()
+    }
+
+    /// Only shorts and short links need fixing, watch links already embed
+    fn detect(&self, url: &Url) -> bool {
+        self.is_post(url) && !matches!(path_segments(url)[..], ["watch"])
+    }
+
+    /// `www.youtube.com/watch?v=<id>`, keeping the timestamp but dropping share tracking
+    fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = Url::parse(&format!("https://{}/watch", YOUTUBE_HOST)).unwrap();
+        let id = self.video_id(url).unwrap_or_default();
+        let time = url.query_pairs().find(|(key, _)| key == "t");
+        {
+            let mut query = canonical.query_pairs_mut();
+            query.append_pair("v", &id);
+            if let Some((_, time)) = time {
+                query.append_pair("t", &time);
+            }
+        }
+        canonical
+    }
+
+    fn post_id(&self, url: &Url) -> Option<String> {
+        self.video_id(url)
+    }
+
+    /// Videos can't be downloaded from the page, only its thumbnail could
+    async fn get_media(&self, _client: &ThoriumClient, _url: &Url) -> Result<Option<MediaBundle>> {
+        Err(ThoriumError::NoMedia)
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use thorium::cache::{CacheConfig, MediaCache};
+use thorium::fetcher::{FetchResponse, Fetcher, ReplayFetcher};
+use thorium::links::parse;
+use thorium::provider::ProviderRegistry;
+use thorium::{
+    ClientConfig, MediaBundle, MediaItem, MediaKind, Result, ThoriumClient, ThoriumConfig,
+    ThoriumError,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Replays the fixtures and counts how often it had to
+struct CountingFetcher {
+    inner: ReplayFetcher,
+    count: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Fetcher for CountingFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        self.inner.fetch(url).await
+    }
+}
+
+fn counting_registry() -> (ProviderRegistry, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let fetcher = CountingFetcher {
+        inner: ReplayFetcher::new(FIXTURES),
+        count: count.clone(),
+    };
+    let config = ClientConfig {
+        max_retries: 0,
+        ..Default::default()
+    };
+    let client = ThoriumClient::with_fetcher(config, fetcher);
+    let registry = ProviderRegistry::with_builtin_providers(client, &ThoriumConfig::default());
+    (registry, count)
+}
+
+fn bundle(url: &str) -> Result<Option<MediaBundle>> {
+    Ok(Some(MediaBundle::new(vec![MediaItem::new(
+        url,
+        MediaKind::Image,
+    )])))
+}
+
+#[tokio::test]
+async fn links_to_the_same_post_share_a_lookup() {
+    let (registry, count) = counting_registry();
+
+    let first = registry
+        .get_media(&parse("https://x.com/sphene/status/1001").unwrap())
+        .await
+        .unwrap();
+    let second = registry
+        .get_media(&parse("https://vxtwitter.com/sphene/status/1001/photo/1?s=20").unwrap())
+        .await
+        .unwrap();
+
+    assert!(first.is_some());
+    assert_eq!(first, second);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn remembers_posts_without_media_but_not_errors() {
+    let (registry, count) = counting_registry();
+
+    let empty = parse("https://x.com/jack/status/20").unwrap();
+    assert_eq!(registry.get_media(&empty).await.unwrap(), None);
+    assert_eq!(registry.get_media(&empty).await.unwrap(), None);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    let unavailable = parse("https://x.com/sphene/status/503").unwrap();
+    assert!(registry.get_media(&unavailable).await.is_err());
+    assert!(registry.get_media(&unavailable).await.is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn keeps_no_media_errors() {
+    let cache = MediaCache::default();
+
+    cache.insert("twitter:1", &Err(ThoriumError::NoMedia));
+    cache.insert("twitter:2", &Err(ThoriumError::Parse("broken".to_string())));
+
+    assert!(matches!(
+        cache.get("twitter:1"),
+        Some(Err(ThoriumError::NoMedia))
+    ));
+    assert!(cache.get("twitter:2").is_none());
+}
+
+#[test]
+fn drops_the_least_recently_used_lookup() {
+    let cache = MediaCache::new(CacheConfig {
+        capacity: 2,
+        ..Default::default()
+    });
+
+    cache.insert("a", &bundle("https://example.com/a.jpg"));
+    cache.insert("b", &bundle("https://example.com/b.jpg"));
+    assert!(cache.get("a").is_some());
+    cache.insert("c", &bundle("https://example.com/c.jpg"));
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("c").is_some());
+}
+
+#[test]
+fn expires_lookups() {
+    let cache = MediaCache::new(CacheConfig {
+        ttl: Duration::ZERO,
+        ..Default::default()
+    });
+
+    cache.insert("a", &bundle("https://example.com/a.jpg"));
+    cache.insert("b", &Ok(None));
+
+    assert!(cache.get("a").is_none());
+    assert!(matches!(cache.get("b"), Some(Ok(None))));
+}
+
+#[test]
+fn persists_lookups_in_the_database() {
+    let path = std::env::temp_dir().join(format!("thorium-cache-{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let cache = MediaCache::with_database(CacheConfig::default(), path).unwrap();
+    cache.insert("twitter:1001", &bundle("https://example.com/a.jpg"));
+    drop(cache);
+
+    let cache = MediaCache::with_database(CacheConfig::default(), path).unwrap();
+    let media = cache.get("twitter:1001").unwrap().unwrap().unwrap();
+    assert_eq!(
+        media.urls().collect::<Vec<_>>(),
+        ["https://example.com/a.jpg"]
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn short_links_are_resolved_once() {
+    let (registry, count) = counting_registry();
+    let short_link = parse("https://vt.tiktok.com/ZSabcdefg/").unwrap();
+
+    let video = registry.resolve(&short_link).await.unwrap();
+    assert_eq!(registry.resolve(&short_link).await.unwrap(), video);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // The short link and the video share their media lookup
+    let first = registry.get_media(&short_link).await.unwrap();
+    let second = registry.get_media(&video).await.unwrap();
+    assert!(first.is_some());
+    assert_eq!(first, second);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,132 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+
+use reqwest::StatusCode;
+use thorium::fetcher::ReplayFetcher;
+use thorium::{ClientConfig, ThoriumClient, ThoriumConfig, ThoriumError};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Answers with a 503, then a 429, then the page
+const FLAKY: &str = "https://flaky.example.com/status";
+
+fn client(max_retries: u32) -> ThoriumClient {
+    let config = ClientConfig {
+        max_retries,
+        retry_backoff: Duration::from_millis(1),
+        ..Default::default()
+    };
+    ThoriumClient::with_fetcher(config, ReplayFetcher::new(FIXTURES))
+}
+
+#[tokio::test]
+async fn retries_transient_failures() {
+    assert_eq!(client(2).get_text(FLAKY).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn gives_up_after_the_last_retry() {
+    assert!(matches!(
+        client(1).get_text(FLAKY).await,
+        Err(ThoriumError::Status(StatusCode::TOO_MANY_REQUESTS))
+    ));
+    assert!(matches!(
+        client(0).get_text(FLAKY).await,
+        Err(ThoriumError::Status(StatusCode::SERVICE_UNAVAILABLE))
+    ));
+}
+
+#[tokio::test]
+async fn missing_pages_are_not_retried() {
+    let client = client(2);
+    assert!(matches!(
+        client.get_text("https://flaky.example.com/gone").await,
+        Err(ThoriumError::Status(StatusCode::NOT_FOUND))
+    ));
+}
+
+/// Serve the body on a port of localhost until the test ends
+fn serve(body: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn names_resolving_to_private_addresses_are_refused() {
+    let url = format!("http://localhost:{}/", serve("local"));
+
+    let client = ThoriumClient::new(ClientConfig {
+        max_retries: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(matches!(
+        client.get_text(&url).await,
+        Err(ThoriumError::Network(_))
+    ));
+
+    // Unless the host is trusted, like a mirror configured to run next to the bot
+    let config = ThoriumConfig::default().with_url("vxtwitter_api", &url);
+    assert_eq!(config.client_config().trusted_hosts, vec!["localhost"]);
+    let client = ThoriumClient::new(config.client_config()).unwrap();
+    assert_eq!(client.get_text(&url).await.unwrap(), "local");
+}
+
+#[tokio::test]
+async fn bodies_are_limited() {
+    let url = format!("http://localhost:{}/", serve("twelve bytes"));
+    let config = |max_body_bytes| ClientConfig {
+        max_body_bytes,
+        trusted_hosts: vec!["localhost".to_string()],
+        ..Default::default()
+    };
+
+    let client = ThoriumClient::new(config(8)).unwrap();
+    assert!(matches!(
+        client.get_text(&url).await,
+        Err(ThoriumError::TooLarge { limit: 8, .. })
+    ));
+    let client = ThoriumClient::new(config(12)).unwrap();
+    assert_eq!(client.get_text(&url).await.unwrap(), "twelve bytes");
+}
+
+#[test]
+fn client_settings_come_from_the_config() {
+    let config = ThoriumConfig::from_toml(
+        "[client]\nuser_agent = \"thorium-test\"\nread_timeout_secs = 3\nmax_retries = 5\nretry_backoff_ms = 250",
+    )
+    .unwrap();
+    let client = config.client_config();
+    assert_eq!(client.user_agent, "thorium-test");
+    assert_eq!(client.read_timeout, Duration::from_secs(3));
+    assert_eq!(client.max_retries, 5);
+    assert_eq!(client.retry_backoff, Duration::from_millis(250));
+    // Settings left out keep their defaults
+    assert_eq!(
+        client.request_timeout,
+        ClientConfig::default().request_timeout
+    );
+
+    assert!(ThoriumConfig::from_toml("[client]\nmax_retries = \"many\"").is_err());
+    assert!(ThoriumConfig::from_toml("[client]\ntimeout = 1").is_err());
+
+    let config = ThoriumConfig::from_toml("[client]\nmax_body_bytes = 1024").unwrap();
+    assert_eq!(config.client_config().max_body_bytes, 1024);
+}
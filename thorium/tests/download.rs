@@ -0,0 +1,131 @@
+use std::{env, fs, path::PathBuf};
+
+use thorium::download::{
+    download_media, download_media_to, sniff, DownloadContent, DownloadLimits,
+};
+use thorium::fetcher::ReplayFetcher;
+use thorium::{ClientConfig, MediaItem, MediaKind, ThoriumClient, ThoriumError};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+const MOUNTAIN: &str =
+    "https://cdn.bsky.app/img/feed_fullsize/plain/did:plc:sphene123/bafkmountain@jpeg";
+
+fn client() -> ThoriumClient {
+    ThoriumClient::with_fetcher(ClientConfig::default(), ReplayFetcher::new(FIXTURES))
+}
+
+/// Path in a directory of its own, so that tests running at the same time don't collide
+fn temp_path(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("thorium-download-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(name)
+}
+
+#[test]
+fn sniffs_formats_from_their_first_bytes() {
+    assert_eq!(
+        sniff(b"#EXTM3U\n"),
+        Some(("application/vnd.apple.mpegurl", "m3u8"))
+    );
+
+    let cases: [(&[u8], Option<&str>); 10] = [
+        (&[0xFF, 0xD8, 0xFF, 0xE0], Some("jpg")),
+        (b"\x89PNG\r\n\x1a\n", Some("png")),
+        (b"GIF89a", Some("gif")),
+        (b"RIFF\0\0\0\0WEBPVP8 ", Some("webp")),
+        (b"\0\0\0\x18ftypmp42", Some("mp4")),
+        (b"\0\0\0\x14ftypqt  ", Some("mov")),
+        (&[0x1A, 0x45, 0xDF, 0xA3], Some("webm")),
+        (b"ID3\x04", Some("mp3")),
+        (b"OggS\0\x02", Some("ogg")),
+        (b"<html>", None),
+    ];
+
+    for (bytes, expected) in cases {
+        let extension = sniff(bytes).map(|(_, extension)| extension);
+        assert_eq!(extension, expected, "{:?}", bytes);
+    }
+}
+
+#[tokio::test]
+async fn downloads_into_memory() {
+    let item = MediaItem::new(MOUNTAIN, MediaKind::Image);
+    let download = download_media(&client(), &item, &DownloadLimits::default())
+        .await
+        .unwrap();
+
+    assert_eq!(download.mime_type, "image/png");
+    assert_eq!(download.extension, "png");
+    assert_eq!(download.kind, MediaKind::Image);
+    assert_eq!(download.file_name, "bafkmountainjpeg.png");
+    let bytes = download.bytes().unwrap();
+    assert_eq!(bytes.len() as u64, download.size);
+    assert!(image::load_from_memory(bytes).is_ok());
+}
+
+#[tokio::test]
+async fn real_format_wins_over_the_link() {
+    let item = MediaItem::from_url("https://media.example.com/clip.jpg");
+    let download = download_media(&client(), &item, &DownloadLimits::default())
+        .await
+        .unwrap();
+
+    assert_eq!(download.mime_type, "video/mp4");
+    assert_eq!(download.kind, MediaKind::Video);
+    assert_eq!(download.file_name, "clip.mp4");
+}
+
+#[tokio::test]
+async fn falls_back_to_the_content_type() {
+    let item = MediaItem::new("https://media.example.com/stream", MediaKind::Video);
+    let download = download_media(&client(), &item, &DownloadLimits::default())
+        .await
+        .unwrap();
+
+    assert_eq!(download.mime_type, "audio/mpeg");
+    assert_eq!(download.kind, MediaKind::Audio);
+    assert_eq!(download.file_name, "stream.mp3");
+}
+
+#[tokio::test]
+async fn refuses_media_over_the_limit() {
+    let item = MediaItem::new(MOUNTAIN, MediaKind::Image);
+    let limits = DownloadLimits::new(64);
+
+    match download_media(&client(), &item, &limits).await {
+        Err(ThoriumError::TooLarge { limit, .. }) => assert_eq!(limit, 64),
+        other => panic!("expected the download to be too large, got {:?}", other),
+    }
+
+    let path = temp_path("too_large");
+    assert!(download_media_to(&client(), &item, &limits, &path)
+        .await
+        .is_err());
+    assert!(!path.with_extension("part").exists());
+    assert!(!path.with_extension("png").exists());
+}
+
+#[tokio::test]
+async fn streams_to_a_file_with_the_real_extension() {
+    let item = MediaItem::from_url("https://media.example.com/clip.jpg");
+    let path = temp_path("clip");
+    let download = download_media_to(&client(), &item, &DownloadLimits::default(), &path)
+        .await
+        .unwrap();
+
+    let file = path.with_extension("mp4");
+    assert_eq!(download.content, DownloadContent::File(file.clone()));
+    assert_eq!(fs::metadata(&file).unwrap().len(), download.size);
+    assert!(!path.with_extension("part").exists());
+    fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn missing_media_is_a_status_error() {
+    let item = MediaItem::from_url("https://media.example.com/gone.mp4");
+    assert!(matches!(
+        download_media(&client(), &item, &DownloadLimits::default()).await,
+        Err(ThoriumError::Status(_))
+    ));
+}
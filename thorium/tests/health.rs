@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use thorium::fetcher::ReplayFetcher;
+use thorium::health::HealthConfig;
+use thorium::links::parse;
+use thorium::provider::ProviderRegistry;
+use thorium::{ClientConfig, ThoriumClient, ThoriumConfig};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Registry replaying the fixtures, health checks never go to the live mirrors
+fn registry(health: HealthConfig) -> ProviderRegistry {
+    let config = ClientConfig {
+        max_retries: 0,
+        ..Default::default()
+    };
+    let client = ThoriumClient::with_fetcher(config, ReplayFetcher::new(FIXTURES));
+    ProviderRegistry::with_builtin_providers(client, &ThoriumConfig::default())
+        .with_health_config(health)
+}
+
+fn preferred_mirror(registry: &ProviderRegistry, provider: &str) -> &'static str {
+    registry
+        .preferred_mirror(registry.get(provider).unwrap())
+        .unwrap()
+        .id
+}
+
+#[test]
+fn falls_back_after_consecutive_failures() {
+    let registry = registry(HealthConfig::default());
+
+    registry.health().record_failure("fixbluesky");
+    registry.health().record_failure("fixbluesky");
+    assert_eq!(preferred_mirror(&registry, "bluesky"), "fixbluesky");
+
+    registry.health().record_failure("fixbluesky");
+    assert_eq!(preferred_mirror(&registry, "bluesky"), "psky");
+
+    registry.health().record_success("fixbluesky");
+    assert_eq!(preferred_mirror(&registry, "bluesky"), "fixbluesky");
+}
+
+#[test]
+fn keeps_the_default_when_every_mirror_is_down() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        ..Default::default()
+    });
+
+    registry.health().record_failure("ddinstagram");
+    assert!(!registry.health().is_healthy("ddinstagram"));
+    assert_eq!(preferred_mirror(&registry, "instagram"), "ddinstagram");
+}
+
+#[test]
+fn retries_mirrors_after_a_while() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        retry_after: Duration::ZERO,
+        ..Default::default()
+    });
+
+    registry.health().record_failure("tiktxk");
+    assert_eq!(preferred_mirror(&registry, "tiktok"), "tiktxk");
+}
+
+#[tokio::test]
+async fn counts_failed_requests() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        ..Default::default()
+    });
+
+    // A post that doesn't exist doesn't mean the mirror is down
+    let missing = parse("https://x.com/sphene/status/404").unwrap();
+    assert!(registry.get_media(&missing).await.is_err());
+    assert_eq!(preferred_mirror(&registry, "twitter"), "vxtwitter");
+
+    let unavailable = parse("https://x.com/sphene/status/503").unwrap();
+    assert!(registry.get_media(&unavailable).await.is_err());
+    assert_eq!(preferred_mirror(&registry, "twitter"), "fxtwitter");
+}
+
+#[tokio::test]
+async fn appview_failures_are_not_held_against_mirrors() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        ..Default::default()
+    });
+
+    for link in [
+        "https://bsyy.app/profile/did:plc:sphene123/post/3kdown503",
+        "https://psky.app/profile/did:plc:sphene123/post/3kdown503",
+    ] {
+        assert!(registry.get_media(&parse(link).unwrap()).await.is_err());
+    }
+    assert!(registry.health().is_healthy("fixbluesky"));
+    assert!(registry.health().is_healthy("psky"));
+}
+
+#[tokio::test]
+async fn reddit_api_failures_are_not_held_against_mirrors() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        ..Default::default()
+    });
+
+    for link in [
+        "https://www.reddit.com/r/pics/comments/1down503/gone/",
+        "https://rxddit.com/r/pics/comments/1down503/gone/",
+    ] {
+        assert!(registry.get_media(&parse(link).unwrap()).await.is_err());
+    }
+    assert!(registry.health().is_healthy("rxddit"));
+    assert_eq!(preferred_mirror(&registry, "reddit"), "rxddit");
+}
+
+#[tokio::test]
+async fn probes_every_mirror() {
+    let registry = registry(HealthConfig {
+        failure_threshold: 1,
+        ..Default::default()
+    });
+
+    registry.probe_mirrors().await;
+
+    // Only the recording of bsyy.app answers with a server error
+    assert!(!registry.health().is_healthy("fixbluesky"));
+    assert!(registry.health().is_healthy("psky"));
+    assert_eq!(preferred_mirror(&registry, "bluesky"), "psky");
+}
@@ -0,0 +1,720 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use reqwest::StatusCode;
+use thorium::fetcher::{RecordingFetcher, ReplayFetcher, ReqwestFetcher};
+use thorium::links::parse;
+use thorium::provider::ProviderRegistry;
+use thorium::{ClientConfig, MediaKind, ThoriumClient, ThoriumConfig, ThoriumError};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn client_config() -> ClientConfig {
+    ClientConfig {
+        max_retries: 1,
+        retry_backoff: Duration::from_millis(1),
+        ..Default::default()
+    }
+}
+
+/// Registry replaying the recorded fixtures, or recording them again when `THORIUM_RECORD` is set
+fn registry() -> ProviderRegistry {
+    let config = client_config();
+
+    let client = if std::env::var_os("THORIUM_RECORD").is_some() {
+        let fetcher = ReqwestFetcher::new(&config).unwrap();
+        ThoriumClient::with_fetcher(config, RecordingFetcher::new(fetcher, FIXTURES))
+    } else {
+        ThoriumClient::with_fetcher(config, ReplayFetcher::new(FIXTURES))
+    };
+
+    ProviderRegistry::with_builtin_providers(client, &ThoriumConfig::default())
+}
+
+/// Registry whose mirrors point to stand-in servers, only ever replayed
+fn stand_in_registry(thorium_config: &ThoriumConfig) -> ProviderRegistry {
+    let client = ThoriumClient::with_fetcher(client_config(), ReplayFetcher::new(FIXTURES));
+    ProviderRegistry::with_builtin_providers(client, thorium_config)
+}
+
+#[tokio::test]
+async fn vxtwitter_without_media() {
+    let url = parse("https://x.com/jack/status/20").unwrap();
+    let registry = registry();
+
+    assert_eq!(registry.get_media(&url).await.unwrap(), None);
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_handle.as_deref(), Some("jack"));
+    assert_eq!(post.text.as_deref(), Some("just setting up my twttr"));
+    assert_eq!(post.created_at, DateTime::from_timestamp(1142974214, 0));
+    assert_eq!(post.likes, Some(306812));
+    assert_eq!(post.reposts, Some(122598));
+    assert_eq!(post.replies, Some(17411));
+    assert_eq!(post.quoted, None);
+}
+
+#[tokio::test]
+async fn vxtwitter_images() {
+    let url = parse("https://twitter.com/sphene/status/1001").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 2);
+    assert!(bundle
+        .items
+        .iter()
+        .all(|item| item.kind == MediaKind::Image));
+    assert_eq!(
+        bundle.items[0].alt_text.as_deref(),
+        Some("A cat sleeping on a keyboard")
+    );
+    assert_eq!(
+        (bundle.items[1].width, bundle.items[1].height),
+        (Some(600), Some(800))
+    );
+    assert_eq!(
+        bundle.mosaic.as_deref(),
+        Some("https://vxtwitter.com/rendercombined.jpg?imgs=https://pbs.twimg.com/media/first.jpg,https://pbs.twimg.com/media/second.jpg")
+    );
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    let quoted = post.quoted.unwrap();
+    assert_eq!(quoted.author_name.as_deref(), Some("Quoted Person"));
+    assert_eq!(quoted.text.as_deref(), Some("the quoted tweet"));
+}
+
+#[tokio::test]
+async fn vxtwitter_keeps_every_video() {
+    let url = parse("https://x.com/sphene/status/1002").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 2);
+    assert!(bundle
+        .items
+        .iter()
+        .all(|item| item.kind == MediaKind::Video));
+    assert_eq!(bundle.items[0].duration, Some(12.5));
+    assert!(bundle.items[0].thumbnail.is_some());
+    assert_eq!(bundle.mosaic, None);
+}
+
+#[tokio::test]
+async fn fxtwitter_mosaic() {
+    let url = parse("https://fxtwitter.com/sphene/status/1001").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    assert_eq!(
+        bundle.mosaic.as_deref(),
+        Some("https://mosaic.fxtwitter.com/jpeg/1001/first/second.jpg")
+    );
+}
+
+#[tokio::test]
+async fn mirrors_are_cached_separately() {
+    let registry = registry();
+    let original = parse("https://twitter.com/sphene/status/1001").unwrap();
+    let mirrored = parse("https://fxtwitter.com/sphene/status/1001").unwrap();
+
+    let bundle = registry.get_media(&original).await.unwrap().unwrap();
+    assert!(bundle.mosaic.unwrap().starts_with("https://vxtwitter.com/"));
+    let bundle = registry.get_media(&mirrored).await.unwrap().unwrap();
+    assert!(bundle
+        .mosaic
+        .unwrap()
+        .starts_with("https://mosaic.fxtwitter.com/"));
+}
+
+#[tokio::test]
+async fn configured_mirror_urls() {
+    let config = ThoriumConfig::default()
+        .with_url("vxtwitter_api", "http://localhost:8080/vx/")
+        .with_url("vxtwitter", "https://vx.example.com");
+    let url = parse("https://x.com/sphene/status/1001").unwrap();
+
+    let bundle = stand_in_registry(&config)
+        .get_media(&url)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bundle.items.len(), 2);
+    assert!(bundle
+        .mosaic
+        .unwrap()
+        .starts_with("https://vx.example.com/rendercombined.jpg?imgs="));
+}
+
+#[tokio::test]
+async fn open_graph_image_and_json_ld() {
+    let url = "https://bsyy.app/profile/sphene.bsky.social/post/3kabc123";
+    let registry = registry();
+    let client = registry.client();
+
+    let bundle = thorium::get_media(client, url.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    let image = bundle.first().unwrap();
+    assert_eq!(image.kind, MediaKind::Image);
+    assert_eq!((image.width, image.height), (Some(2000), Some(1500)));
+    assert_eq!(image.alt_text.as_deref(), Some("A mountain at sunset"));
+
+    let post = thorium::get_post(client, url.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("Sphene"));
+    assert_eq!(post.author_handle.as_deref(), Some("sphene.bsky.social"));
+    assert_eq!(post.likes, Some(42));
+    assert_eq!(post.reposts, Some(7));
+    assert_eq!(post.language.as_deref(), Some("en"));
+}
+
+#[tokio::test]
+async fn open_graph_video() {
+    let url = parse("https://www.tiktok.com/@scout2015/video/6718335390845095173").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 1);
+
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert_eq!(
+        video.url,
+        "https://tiktxk.com/meta/6718335390845095173/video.mp4"
+    );
+    assert_eq!(video.mime_type.as_deref(), Some("video/mp4"));
+    assert_eq!(
+        video.thumbnail.as_deref(),
+        Some("https://tiktxk.com/meta/6718335390845095173/thumbnail")
+    );
+}
+
+#[tokio::test]
+async fn empty_media_tags_are_no_media() {
+    let url = parse("https://www.instagram.com/p/C1a2B3c4D5e/").unwrap();
+
+    let error = registry().get_media(&url).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::NoMedia), "{error}");
+}
+
+#[tokio::test]
+async fn status_errors_are_typed() {
+    let registry = registry();
+
+    let unavailable = parse("https://x.com/sphene/status/503").unwrap();
+    let error = registry.get_media(&unavailable).await.unwrap_err();
+    assert!(
+        matches!(error, ThoriumError::Status(StatusCode::SERVICE_UNAVAILABLE)),
+        "{error}"
+    );
+
+    let missing = parse("https://x.com/sphene/status/404").unwrap();
+    let error = registry.get_media(&missing).await.unwrap_err();
+    assert!(
+        matches!(error, ThoriumError::Status(StatusCode::NOT_FOUND)),
+        "{error}"
+    );
+
+    let unsupported = parse("https://example.com/").unwrap();
+    let error = registry.get_media(&unsupported).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::UnsupportedUrl(_)), "{error}");
+}
+
+#[tokio::test]
+async fn reddit_gallery() {
+    let url = parse("https://www.reddit.com/r/pics/comments/1gal123/two_views/").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    // The failed upload is skipped
+    assert_eq!(bundle.items.len(), 2);
+    assert_eq!(bundle.items[0].kind, MediaKind::Image);
+    assert_eq!(bundle.items[0].width, Some(1080));
+    assert_eq!(bundle.items[0].alt_text.as_deref(), Some("Morning"));
+    assert_eq!(bundle.items[1].kind, MediaKind::Gif);
+    assert_eq!(
+        bundle.items[1].url,
+        "https://preview.redd.it/abc222.gif?format=mp4"
+    );
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_handle.as_deref(), Some("sphene_tester"));
+    assert_eq!(post.text.as_deref(), Some("Two views of the same lake"));
+    assert_eq!(post.likes, Some(1337));
+    assert_eq!(post.replies, Some(42));
+}
+
+#[tokio::test]
+async fn reddit_crossposted_video() {
+    let url = parse("https://redd.it/1vid456").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert_eq!(
+        video.url,
+        "https://v.redd.it/cat123/DASH_1080.mp4?source=fallback"
+    );
+    assert_eq!(video.duration, Some(14.0));
+    assert_eq!(
+        video.thumbnail.as_deref(),
+        Some("https://external-preview.redd.it/cat.jpg")
+    );
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_handle.as_deref(), Some("sphene_reposter"));
+    let quoted = post.quoted.unwrap();
+    assert_eq!(quoted.author_handle.as_deref(), Some("cat_owner"));
+    assert_eq!(
+        quoted.text.as_deref(),
+        Some("My cat learned to open doors\n\nHe is very proud of it.")
+    );
+}
+
+#[tokio::test]
+async fn reddit_share_link() {
+    let url = parse("https://www.reddit.com/r/pics/s/AbCdEf12").unwrap();
+    let registry = registry();
+
+    // Resolved before the lookup, so the post is cached under its id
+    assert_eq!(
+        registry.resolve(&url).await.unwrap().as_str(),
+        "https://www.reddit.com/r/pics/comments/1gal123/two_views_of_the_same_lake/"
+    );
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 2);
+    assert!(registry.cache().get("reddit:1gal123").is_some());
+}
+
+#[tokio::test]
+async fn fediverse_instances_are_verified() {
+    let registry = registry();
+
+    let status = parse("https://mastodon.social/@sphene/113000000000000001").unwrap();
+    // Only owned once the host is known to be an instance
+    assert!(registry.find(&status).is_none());
+    let provider = registry.detect_verified(&status).await.unwrap();
+    assert_eq!(provider.id(), "fediverse");
+    assert_eq!(registry.find(&status).unwrap().id(), "fediverse");
+
+    let note = parse("https://misskey.io/notes/9xyz0123ab").unwrap();
+    assert!(registry.detect_verified(&note).await.is_some());
+
+    // Hosts answering without NodeInfo, or not at all, aren't instances
+    let blog = parse("https://example.com/@sphene/113000000000000001").unwrap();
+    assert!(registry.detect(&blog).is_some());
+    assert!(registry.detect_verified(&blog).await.is_none());
+    let unknown = parse("https://example.org/@sphene/113000000000000001").unwrap();
+    assert!(registry.detect_verified(&unknown).await.is_none());
+    // Hosts on the local network aren't asked, even though this one would answer
+    let private = parse("https://10.0.0.1/@admin/113000000000000001").unwrap();
+    assert!(registry.detect_verified(&private).await.is_none());
+
+    // Other sites with paths of the same shape are left to the fallback
+    let channel = parse("https://youtube.com/@chan/12345").unwrap();
+    assert!(registry.find(&channel).is_none());
+    assert_eq!(
+        registry.find_or_fallback(&channel).await.unwrap().id(),
+        "generic"
+    );
+}
+
+#[tokio::test]
+async fn fediverse_status_media() {
+    let url = parse("https://mastodon.social/@sphene/113000000000000001").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 2);
+
+    let image = &bundle.items[0];
+    assert_eq!(image.kind, MediaKind::Image);
+    assert_eq!(
+        image.alt_text.as_deref(),
+        Some("A lake at sunrise with fog over the water")
+    );
+    assert_eq!((image.width, image.height), (Some(1920), Some(1080)));
+    assert!(image.thumbnail.as_deref().unwrap().contains("/small/"));
+
+    let gif = &bundle.items[1];
+    assert_eq!(gif.kind, MediaKind::Gif);
+    assert_eq!(gif.duration, Some(2.5));
+    assert_eq!(gif.alt_text, None);
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("Sphene"));
+    assert_eq!(
+        post.author_handle.as_deref(),
+        Some("sphene@mastodon.social")
+    );
+    assert_eq!(
+        post.text.as_deref(),
+        Some("Morning at the lake\n\nSecond paragraph\nwith a break")
+    );
+    assert_eq!(post.created_at, DateTime::from_timestamp(1725193800, 0));
+    assert_eq!(post.likes, Some(45));
+    assert_eq!(post.reposts, Some(12));
+    assert_eq!(post.replies, Some(3));
+    assert_eq!(post.language.as_deref(), Some("en"));
+}
+
+#[tokio::test]
+async fn fediverse_notes_fall_back_to_embeds() {
+    let url = parse("https://misskey.io/notes/9xyz0123ab").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    assert_eq!(
+        bundle.urls().collect::<Vec<_>>(),
+        ["https://media.misskey.io/files/cat.webp"]
+    );
+}
+
+#[tokio::test]
+async fn threads_video() {
+    let url = parse("https://www.threads.net/@sphene/post/C9abcDEF123").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert_eq!(video.url, "https://fixthreads.net/video/C9abcDEF123/1.mp4");
+    assert_eq!((video.width, video.height), (Some(720), Some(1280)));
+    assert_eq!(
+        video.thumbnail.as_deref(),
+        Some("https://fixthreads.net/thumbnail/C9abcDEF123/1.jpg")
+    );
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.text.as_deref(), Some("Ducks at the pond"));
+}
+
+#[tokio::test]
+async fn pixiv_returns_every_page() {
+    let url = parse("https://www.pixiv.net/en/artworks/120000001").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 3);
+    assert!(bundle
+        .items
+        .iter()
+        .all(|item| item.kind == MediaKind::Image));
+    assert!(bundle.items[2].url.ends_with("120000001_p2_master1200.jpg"));
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("sphene"));
+    assert_eq!(
+        post.text.as_deref(),
+        Some("Summer sketches\n\nThree pages from the sketchbook\nThanks for looking!")
+    );
+}
+
+#[tokio::test]
+async fn tumblr_photoset() {
+    let url = parse("https://sphene.tumblr.com/post/700000000000000001/pond-studies").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 3);
+    assert_eq!(bundle.items[0].alt_text.as_deref(), Some("The first duck"));
+    assert_eq!(
+        (bundle.items[1].width, bundle.items[1].height),
+        (Some(960), Some(1280))
+    );
+    assert!(bundle.items[2].url.ends_with("pond3.png"));
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_handle.as_deref(), Some("sphene"));
+    assert_eq!(post.author_name.as_deref(), Some("Sphene's sketches"));
+    assert_eq!(post.text.as_deref(), Some("Pond studies"));
+    assert_eq!(post.created_at, DateTime::from_timestamp(1717236000, 0));
+}
+
+#[tokio::test]
+async fn tumblr_reblog() {
+    let url = parse("https://www.tumblr.com/sphene/700000000000000002").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    assert_eq!(
+        bundle.urls().collect::<Vec<_>>(),
+        [
+            "https://64.media.tumblr.com/ddd/s1280x1920/duck.jpg",
+            "https://va.media.tumblr.com/tumblr_duck.mp4",
+        ]
+    );
+    assert_eq!(bundle.items[0].alt_text.as_deref(), Some("A duck"));
+    assert_eq!(bundle.items[1].kind, MediaKind::Video);
+    assert_eq!(bundle.items[1].mime_type.as_deref(), Some("video/mp4"));
+}
+
+#[tokio::test]
+async fn twitch_clip_from_thumbnail() {
+    let url = parse("https://www.twitch.tv/sphene/clip/DuckyPondClip-a1B2c3D4").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert_eq!(
+        video.url,
+        "https://clips-media-assets2.twitch.tv/AT-cm%7C123456.mp4"
+    );
+    assert_eq!(
+        video.thumbnail.as_deref(),
+        Some("https://clips-media-assets2.twitch.tv/AT-cm%7C123456-preview-480x272.jpg")
+    );
+
+    // Newer clips need a signed token, their thumbnail is all the page reveals
+    let newer = parse("https://clips.twitch.tv/NewerClip-x9Y8z7").unwrap();
+    assert_eq!(registry.get_media(&newer).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn youtube_has_nothing_to_download() {
+    let url = parse("https://www.youtube.com/shorts/dQw4w9WgXcQ").unwrap();
+
+    let error = registry().get_media(&url).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::NoMedia), "{error}");
+}
+
+#[tokio::test]
+async fn tiktok_short_links_are_resolved() {
+    let registry = registry();
+    let video = "https://www.tiktok.com/@scout2015/video/6718335390845095173";
+
+    for short_link in [
+        "https://vt.tiktok.com/ZSabcdefg/",
+        "https://www.tiktok.com/t/ZTRxyz123/?_r=1",
+    ] {
+        let url = parse(short_link).unwrap();
+        // Share tracking is dropped along the way
+        assert_eq!(registry.resolve(&url).await.unwrap().as_str(), video);
+        assert!(registry.get_media(&url).await.unwrap().is_some());
+    }
+
+    // Short links of removed videos lead to the front page
+    let gone = parse("https://vm.tiktok.com/ZMgone/").unwrap();
+    let error = registry.resolve(&gone).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::UnsupportedUrl(_)), "{error}");
+}
+
+#[tokio::test]
+async fn instagram_share_links_are_resolved() {
+    let url = parse("https://www.instagram.com/share/reel/BAbCdEfGh1/?igsh=MTc4").unwrap();
+    let registry = registry();
+
+    assert_eq!(
+        registry.resolve(&url).await.unwrap().as_str(),
+        "https://www.instagram.com/reel/C9zYxWvUtS1/"
+    );
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert_eq!(
+        video.url,
+        "https://www.ddinstagram.com/videos/C9zYxWvUtS1/1"
+    );
+}
+
+#[tokio::test]
+async fn bluesky_images_and_quote_from_the_appview() {
+    let url = parse("https://bsky.app/profile/sphene.bsky.social/post/3kabc123").unwrap();
+    let registry = registry();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    assert_eq!(bundle.items.len(), 2);
+
+    let image = &bundle.items[0];
+    assert_eq!(image.kind, MediaKind::Image);
+    assert_eq!(
+        image.url,
+        "https://cdn.bsky.app/img/feed_fullsize/plain/did:plc:sphene123/bafkmountain@jpeg"
+    );
+    assert_eq!((image.width, image.height), (Some(2000), Some(1500)));
+    assert_eq!(image.alt_text.as_deref(), Some("A mountain at sunset"));
+    assert_eq!(bundle.items[1].alt_text, None);
+
+    let post = registry.get_post(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("Sphene"));
+    assert_eq!(post.author_handle.as_deref(), Some("sphene.bsky.social"));
+    assert_eq!(post.text.as_deref(), Some("Two views of the same lake"));
+    assert_eq!(post.created_at, DateTime::from_timestamp(1725265800, 0));
+    assert_eq!(post.likes, Some(42));
+    assert_eq!(post.reposts, Some(7));
+    assert_eq!(post.replies, Some(3));
+    assert_eq!(post.language.as_deref(), Some("en"));
+
+    let quoted = post.quoted.unwrap();
+    assert_eq!(quoted.author_handle.as_deref(), Some("lake.bsky.social"));
+    assert_eq!(
+        quoted.text.as_deref(),
+        Some("Fog over the lake this morning")
+    );
+    assert_eq!(quoted.likes, Some(120));
+}
+
+#[tokio::test]
+async fn bluesky_quotes_show_the_quoted_media() {
+    let url = parse("https://bsky.app/profile/sphene.bsky.social/post/3kquote789").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    let image = bundle.first().unwrap();
+    assert_eq!(
+        image.url,
+        "https://cdn.bsky.app/img/feed_fullsize/plain/did:plc:other456/bafkfog@jpeg"
+    );
+    assert_eq!(image.alt_text.as_deref(), Some("Foggy lake"));
+}
+
+#[tokio::test]
+async fn bluesky_video_from_a_did_link() {
+    let url = parse("https://bsky.app/profile/did:plc:sphene123/post/3kvid456").unwrap();
+
+    let bundle = registry().get_media(&url).await.unwrap().unwrap();
+    let video = bundle.first().unwrap();
+    assert_eq!(video.kind, MediaKind::Video);
+    assert!(video.url.ends_with("/bafkvideo/playlist.m3u8"));
+    assert!(video
+        .thumbnail
+        .as_deref()
+        .unwrap()
+        .ends_with("/thumbnail.jpg"));
+    assert_eq!((video.width, video.height), (Some(1080), Some(1920)));
+    assert_eq!(video.alt_text.as_deref(), Some("Waves on the shore"));
+}
+
+#[tokio::test]
+async fn bluesky_configured_appview() {
+    let config = ThoriumConfig::default().with_url("bsky_appview", "http://localhost:8080/bsky/");
+    let url = parse("https://bsky.app/profile/did:plc:sphene123/post/3kvid456").unwrap();
+
+    let bundle = stand_in_registry(&config)
+        .get_media(&url)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bundle.first().unwrap().kind, MediaKind::Video);
+
+    // Handles that don't resolve are reported as such
+    let unknown = parse("https://bsky.app/profile/nobody.bsky.social/post/3kabc123").unwrap();
+    let error = registry().get_media(&unknown).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::Status(_)), "{error}");
+}
+
+#[tokio::test]
+async fn generic_pages_fall_back_to_oembed_and_open_graph() {
+    let registry = registry();
+    let url = parse("https://blog.example.com/posts/hello#comments").unwrap();
+    assert_eq!(
+        registry.find_or_fallback(&url).await.unwrap().id(),
+        "generic"
+    );
+
+    // The oEmbed names the author, the page fills in the rest
+    let post = registry.get_post_or_fallback(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("Ada Lovelace"));
+    assert_eq!(post.text.as_deref(), Some("Hello world"));
+    assert_eq!(post.created_at, DateTime::from_timestamp(1714564800, 0));
+    assert_eq!(post.language.as_deref(), Some("en"));
+
+    let bundle = registry.get_media_or_fallback(&url).await.unwrap().unwrap();
+    let image = bundle.first().unwrap();
+    assert_eq!(image.url, "https://blog.example.com/images/hello.png");
+    assert_eq!((image.width, image.height), (Some(1200), Some(630)));
+
+    // Only asked for explicitly
+    let error = registry.get_media(&url).await.unwrap_err();
+    assert!(matches!(error, ThoriumError::UnsupportedUrl(_)), "{error}");
+}
+
+#[tokio::test]
+async fn generic_pages_with_twitter_cards() {
+    let url = parse("https://cards.example.com/article").unwrap();
+    let registry = registry();
+
+    let post = registry.get_post_or_fallback(&url).await.unwrap().unwrap();
+    assert_eq!(post.text.as_deref(), Some("Card title\n\nCard description"));
+    assert_eq!(post.author_handle.as_deref(), Some("ada"));
+
+    let bundle = registry.get_media_or_fallback(&url).await.unwrap().unwrap();
+    assert_eq!(
+        bundle.urls().collect::<Vec<_>>(),
+        ["https://cards.example.com/card.jpg"]
+    );
+}
+
+#[tokio::test]
+async fn generic_pages_from_the_oembed_registry() {
+    let url = parse("https://vimeo.com/76979871").unwrap();
+    let registry = registry();
+
+    let post = registry.get_post_or_fallback(&url).await.unwrap().unwrap();
+    assert_eq!(post.author_name.as_deref(), Some("Vimeo Staff"));
+
+    // Videos only come as players, so their thumbnail stands in for them
+    let bundle = registry.get_media_or_fallback(&url).await.unwrap().unwrap();
+    let thumbnail = bundle.first().unwrap();
+    assert_eq!(
+        thumbnail.url,
+        "https://i.vimeocdn.com/video/452001751-640.jpg"
+    );
+    assert_eq!(thumbnail.kind, MediaKind::Image);
+}
+
+#[tokio::test]
+async fn generic_fallback_leaves_provider_links_alone() {
+    let registry = registry();
+    let twitter = parse("https://x.com/sphene/status/1").unwrap();
+    assert_eq!(
+        registry.find_or_fallback(&twitter).await.unwrap().id(),
+        "twitter"
+    );
+
+    for url in [
+        "http://localhost:8080/page",
+        "http://localhost./page",
+        "http://intranet/page",
+        "http://127.0.0.1/page",
+        "http://10.0.0.1/page",
+        "http://192.168.1.1/page",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/page",
+        "http://[::ffff:127.0.0.1]/page",
+        "http://[fd00::1]/page",
+        "http://[fe80::1]/page",
+    ] {
+        assert!(
+            registry
+                .find_or_fallback(&parse(url).unwrap())
+                .await
+                .is_none(),
+            "{url}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn generic_pages_cannot_reach_private_hosts() {
+    let registry = registry();
+
+    // Neither the announced oEmbed endpoint nor the image point to the internet
+    let page = parse("https://sneaky.example.com/post").unwrap();
+    assert!(registry
+        .get_media_or_fallback(&page)
+        .await
+        .unwrap()
+        .is_none());
+    let post = registry.get_post_or_fallback(&page).await.unwrap().unwrap();
+    assert_eq!(post.text.as_deref(), Some("Nothing to see"));
+    assert_eq!(post.author_name, None);
+
+    // Pages that redirected to a private host are dropped
+    let redirected = parse("https://redirect.example.com/post").unwrap();
+    assert!(matches!(
+        registry.get_media_or_fallback(&redirected).await,
+        Err(ThoriumError::UnsupportedUrl(_))
+    ));
+}
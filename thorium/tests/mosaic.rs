@@ -0,0 +1,150 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use thorium::fetcher::ReplayFetcher;
+use thorium::links::parse;
+use thorium::mosaic::{compose, compose_images, MosaicConfig, MosaicFormat};
+use thorium::provider::ProviderRegistry;
+use thorium::{
+    ClientConfig, MediaBundle, MediaItem, MediaKind, ThoriumClient, ThoriumConfig, ThoriumError,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn image(width: u32, height: u32, color: [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb(color)))
+}
+
+fn decode(data: &[u8]) -> DynamicImage {
+    image::load_from_memory(data).unwrap()
+}
+
+#[test]
+fn two_images_side_by_side() {
+    let config = MosaicConfig {
+        gap: 0,
+        ..Default::default()
+    };
+    let images = [image(100, 100, [255, 0, 0]), image(100, 100, [0, 0, 255])];
+
+    let mosaic = compose_images(&images, &config).unwrap();
+    assert_eq!((mosaic.width, mosaic.height), (200, 100));
+    assert_eq!(mosaic.file_name(), "mosaic.jpg");
+
+    let decoded = decode(&mosaic.data);
+    assert!(decoded.get_pixel(50, 50)[0] > 200);
+    assert!(decoded.get_pixel(150, 50)[2] > 200);
+}
+
+#[test]
+fn three_images_have_a_large_first_one() {
+    let config = MosaicConfig {
+        gap: 10,
+        format: MosaicFormat::WebP,
+        ..Default::default()
+    };
+    let images = [
+        image(100, 100, [255, 0, 0]),
+        image(100, 100, [0, 255, 0]),
+        image(100, 100, [0, 0, 255]),
+    ];
+
+    let mosaic = compose_images(&images, &config).unwrap();
+    assert_eq!((mosaic.width, mosaic.height), (210, 210));
+    assert_eq!(mosaic.format.mime_type(), "image/webp");
+
+    // Lossless, so the colors are exact
+    let decoded = decode(&mosaic.data).to_rgb8();
+    assert_eq!(decoded.get_pixel(50, 200), &Rgb([255, 0, 0]));
+    assert_eq!(decoded.get_pixel(160, 50), &Rgb([0, 255, 0]));
+    assert_eq!(decoded.get_pixel(160, 160), &Rgb([0, 0, 255]));
+    assert_eq!(decoded.get_pixel(105, 50), &Rgb([0, 0, 0]));
+}
+
+#[test]
+fn four_images_in_a_grid_within_the_size_caps() {
+    let config = MosaicConfig {
+        max_width: 1000,
+        max_height: 400,
+        gap: 0,
+        ..Default::default()
+    };
+    let images: Vec<_> = (0..5).map(|_| image(1200, 800, [9, 9, 9])).collect();
+
+    let mosaic = compose_images(&images, &config).unwrap();
+    assert_eq!((mosaic.width, mosaic.height), (1000, 400));
+}
+
+#[test]
+fn single_images_are_not_a_mosaic() {
+    let config = MosaicConfig::default();
+    assert!(compose_images(&[image(10, 10, [0, 0, 0])], &config).is_err());
+}
+
+#[test]
+fn oversized_mosaics_are_rejected() {
+    let config = MosaicConfig {
+        format: MosaicFormat::WebP,
+        max_bytes: 16,
+        ..Default::default()
+    };
+    let images = [image(64, 64, [1, 2, 3]), image(64, 64, [3, 2, 1])];
+    assert!(compose_images(&images, &config).is_err());
+}
+
+#[tokio::test]
+async fn composes_the_images_of_a_post() {
+    let client = ThoriumClient::with_fetcher(ClientConfig::default(), ReplayFetcher::new(FIXTURES));
+    let registry = ProviderRegistry::with_builtin_providers(client, &ThoriumConfig::default());
+    let url = parse("https://bsky.app/profile/sphene.bsky.social/post/3kabc123").unwrap();
+
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+    let mosaic = compose(registry.client(), &bundle, &MosaicConfig::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        decode(&mosaic.data).dimensions(),
+        (mosaic.width, mosaic.height)
+    );
+    assert_eq!(mosaic.width, 400 + 4 + 400);
+
+    // Videos and lone images aren't combined
+    let mut single = MediaBundle::new(vec![bundle.items[0].clone()]);
+    single.items.push(MediaItem::new(
+        "https://example.com/clip.mp4",
+        MediaKind::Video,
+    ));
+    assert!(
+        compose(registry.client(), &single, &MosaicConfig::default())
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn oversized_images_are_refused() {
+    let client = ThoriumClient::with_fetcher(ClientConfig::default(), ReplayFetcher::new(FIXTURES));
+    let registry = ProviderRegistry::with_builtin_providers(client, &ThoriumConfig::default());
+    let url = parse("https://bsky.app/profile/sphene.bsky.social/post/3kabc123").unwrap();
+    let bundle = registry.get_media(&url).await.unwrap().unwrap();
+
+    // Too large to download
+    let config = MosaicConfig {
+        max_image_bytes: 64,
+        ..Default::default()
+    };
+    assert!(matches!(
+        compose(registry.client(), &bundle, &config).await,
+        Err(ThoriumError::TooLarge { limit: 64, .. })
+    ));
+
+    // Too large once decoded
+    let config = MosaicConfig {
+        max_image_dimension: 100,
+        ..Default::default()
+    };
+    assert!(matches!(
+        compose(registry.client(), &bundle, &config).await,
+        Err(ThoriumError::Parse(_))
+    ));
+}
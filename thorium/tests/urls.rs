@@ -0,0 +1,666 @@
+use thorium::links::{find_links, parse, replace_links, rewrite_links};
+use thorium::provider::ProviderRegistry;
+use thorium::{ThoriumClient, ThoriumConfig};
+
+/// Link, provider that should detect it (None if no provider should)
+const DETECTION_CASES: &[(&str, Option<&str>)] = &[
+    // Twitter
+    ("https://twitter.com/jack/status/20", Some("twitter")),
+    ("https://x.com/jack/status/20", Some("twitter")),
+    ("http://x.com/jack/status/20", Some("twitter")),
+    ("https://X.COM/jack/status/20", Some("twitter")),
+    (
+        "https://www.twitter.com/jack/status/20?s=20&t=abc",
+        Some("twitter"),
+    ),
+    ("https://mobile.twitter.com/jack/status/20", Some("twitter")),
+    ("https://x.com/jack/status/20/photo/1", Some("twitter")),
+    ("https://x.com/i/web/status/20", Some("twitter")),
+    ("https://x.com/jack", None),
+    ("https://x.com/jack/status/notanid", None),
+    ("https://notx.com/jack/status/20", None),
+    ("https://example.com/?u=https://x.com/jack/status/20", None),
+    // Other frontends stand for the original site
+    ("https://fixupx.com/jack/status/20", Some("twitter")),
+    ("https://fixvx.com/jack/status/20", Some("twitter")),
+    ("https://girlcockx.com/jack/status/20", Some("twitter")),
+    ("https://nitter.poast.org/jack/status/20#m", Some("twitter")),
+    ("https://xcancel.com/jack/status/20", Some("twitter")),
+    ("https://d.fxtwitter.com/jack/status/20", Some("twitter")),
+    ("https://d.fixupx.com/jack/status/20.mp4", Some("twitter")),
+    ("https://fixupx.com/jack", None),
+    ("https://nitter.net/jack/status/notanid", None),
+    // Mirrors are never detected as original links
+    ("https://vxtwitter.com/jack/status/20", None),
+    ("https://fxtwitter.com/jack/status/20", None),
+    ("https://fxtwitter.com/jack/status/20.jpg", None),
+    // Bluesky
+    (
+        "https://bsky.app/profile/bsky.app/post/3l6oveex3ii2l",
+        Some("bluesky"),
+    ),
+    (
+        "https://BSKY.app/profile/did:plc:z72i7hdynmk6r22z27h6tvur/post/3l6oveex3ii2l",
+        Some("bluesky"),
+    ),
+    ("https://bsky.app/profile/bsky.app", None),
+    ("https://bsyy.app/profile/bsky.app/post/3l6oveex3ii2l", None),
+    // Instagram
+    (
+        "https://www.instagram.com/p/C1a2B3c4D5e/",
+        Some("instagram"),
+    ),
+    ("https://instagram.com/p/C1a2B3c4D5e/", Some("instagram")),
+    (
+        "https://www.instagram.com/reel/C1a2B3c4D5e/?igsh=abc",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/reels/C1a2B3c4D5e/",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/tv/C1a2B3c4D5e/",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/someone/p/C1a2B3c4D5e/",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/stories/someone/3412345678901234567/",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/share/BAbCdEfGh1/",
+        Some("instagram"),
+    ),
+    (
+        "https://www.instagram.com/share/reel/BAbCdEfGh1/",
+        Some("instagram"),
+    ),
+    ("https://www.instagram.com/someone/", None),
+    (
+        "https://www.instagram.com/stories/highlights/17900000000000000/",
+        None,
+    ),
+    ("https://www.ddinstagram.com/p/C1a2B3c4D5e/", None),
+    // TikTok
+    (
+        "https://www.tiktok.com/@scout2015/video/6718335390845095173",
+        Some("tiktok"),
+    ),
+    (
+        "https://tiktok.com/@scout2015/video/6718335390845095173",
+        Some("tiktok"),
+    ),
+    (
+        "https://m.tiktok.com/@scout2015/video/6718335390845095173",
+        Some("tiktok"),
+    ),
+    ("https://vt.tiktok.com/ZSabcdefg/", Some("tiktok")),
+    ("https://vm.tiktok.com/ZMabcdefg/", Some("tiktok")),
+    ("https://www.tiktok.com/t/ZTRxyz123/", Some("tiktok")),
+    ("https://www.tiktok.com/@scout2015", None),
+    (
+        "https://tiktxk.com/@scout2015/video/6718335390845095173",
+        None,
+    ),
+    // Reddit
+    (
+        "https://www.reddit.com/r/pics/comments/1gal123/two_views/",
+        Some("reddit"),
+    ),
+    (
+        "https://old.reddit.com/r/pics/comments/1gal123/",
+        Some("reddit"),
+    ),
+    ("https://reddit.com/comments/1gal123", Some("reddit")),
+    (
+        "https://www.reddit.com/user/someone/comments/1gal123/",
+        Some("reddit"),
+    ),
+    ("https://www.reddit.com/r/pics/s/AbCdEf12", Some("reddit")),
+    ("https://redd.it/1gal123", Some("reddit")),
+    ("https://www.reddit.com/r/pics/", None),
+    ("https://www.reddit.com/r/pics/comments/", None),
+    ("https://rxddit.com/r/pics/comments/1gal123/", None),
+    // Threads
+    (
+        "https://www.threads.net/@zuck/post/C9abcDEF123",
+        Some("threads"),
+    ),
+    (
+        "https://www.threads.com/@zuck/post/C9abcDEF123/media",
+        Some("threads"),
+    ),
+    ("https://threads.net/t/C9abcDEF123", Some("threads")),
+    ("https://www.threads.net/@zuck", None),
+    ("https://fixthreads.net/@zuck/post/C9abcDEF123", None),
+    // Pixiv
+    ("https://www.pixiv.net/artworks/120000001", Some("pixiv")),
+    ("https://www.pixiv.net/en/artworks/120000001", Some("pixiv")),
+    ("https://pixiv.net/ja/artworks/120000001", Some("pixiv")),
+    ("https://www.pixiv.net/en/users/1000001", None),
+    ("https://www.pixiv.net/artworks/notanid", None),
+    ("https://www.phixiv.net/artworks/120000001", None),
+    // Tumblr
+    (
+        "https://sphene.tumblr.com/post/700000000000000001/pond-studies",
+        Some("tumblr"),
+    ),
+    (
+        "https://www.tumblr.com/sphene/700000000000000001",
+        Some("tumblr"),
+    ),
+    (
+        "https://tumblr.com/blog/view/sphene/700000000000000001",
+        Some("tumblr"),
+    ),
+    ("https://sphene.tumblr.com/", None),
+    ("https://www.tumblr.com/sphene", None),
+    ("https://www.tumblr.com/post/700000000000000001", None),
+    // Twitch
+    (
+        "https://clips.twitch.tv/DuckyPondClip-a1B2c3D4",
+        Some("twitch"),
+    ),
+    (
+        "https://www.twitch.tv/sphene/clip/DuckyPondClip-a1B2c3D4?filter=clips",
+        Some("twitch"),
+    ),
+    ("https://www.twitch.tv/sphene", None),
+    (
+        "https://clips.twitch.tv/embed?clip=DuckyPondClip-a1B2c3D4",
+        None,
+    ),
+    // YouTube
+    (
+        "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+        Some("youtube"),
+    ),
+    ("https://youtu.be/dQw4w9WgXcQ?si=tracking", Some("youtube")),
+    ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", None),
+    ("https://www.youtube.com/@sphene", None),
+    // Fediverse, any host with the shape of a post
+    (
+        "https://mastodon.social/@sphene/113000000000000001",
+        Some("fediverse"),
+    ),
+    (
+        "https://chaos.social/@sphene@mastodon.social/113000000000000001",
+        Some("fediverse"),
+    ),
+    (
+        "https://mastodon.social/users/sphene/statuses/113000000000000001",
+        Some("fediverse"),
+    ),
+    ("https://misskey.io/notes/9xyz0123ab", Some("fediverse")),
+    ("https://mastodon.social/@sphene", None),
+    ("https://mastodon.social/@sphene/with_replies", None),
+    ("https://mastodon.social/@/113000000000000001", None),
+];
+
+#[test]
+fn detects_providers() {
+    let registry = ProviderRegistry::default();
+
+    for (link, expected) in DETECTION_CASES {
+        let url = parse(link).unwrap_or_else(|| panic!("{link} should parse"));
+        let detected = registry.detect(&url).map(|provider| provider.id());
+        assert_eq!(detected, *expected, "{link}");
+    }
+}
+
+/// Link, mirror id, expected rewritten link
+const REWRITE_CASES: &[(&str, &str, &str)] = &[
+    (
+        "https://x.com/jack/status/20?s=20",
+        "vxtwitter",
+        "https://vxtwitter.com/jack/status/20?s=20",
+    ),
+    (
+        "http://MOBILE.twitter.com/jack/status/20",
+        "fxtwitter",
+        "https://fxtwitter.com/jack/status/20",
+    ),
+    (
+        "https://bsky.app/profile/bsky.app/post/3l6oveex3ii2l",
+        "psky",
+        "https://psky.app/profile/bsky.app/post/3l6oveex3ii2l",
+    ),
+    (
+        "https://instagram.com/p/C1a2B3c4D5e/",
+        "ddinstagram",
+        "https://www.ddinstagram.com/p/C1a2B3c4D5e/",
+    ),
+    (
+        "https://tiktok.com/@scout2015/video/6718335390845095173",
+        "tnktok",
+        "https://tnktok.com/@scout2015/video/6718335390845095173",
+    ),
+    (
+        "https://old.reddit.com/r/pics/comments/1gal123/two_views/",
+        "rxddit",
+        "https://rxddit.com/r/pics/comments/1gal123/two_views/",
+    ),
+    (
+        "https://redd.it/1gal123",
+        "vxreddit",
+        "https://vxreddit.com/comments/1gal123",
+    ),
+    (
+        "https://www.threads.net/@zuck/post/C9abcDEF123",
+        "fixthreads",
+        "https://fixthreads.net/@zuck/post/C9abcDEF123",
+    ),
+    (
+        "https://threads.com/t/C9abcDEF123",
+        "vxthreads",
+        "https://vxthreads.net/t/C9abcDEF123",
+    ),
+    (
+        "https://pixiv.net/en/artworks/120000001",
+        "phixiv",
+        "https://www.phixiv.net/en/artworks/120000001",
+    ),
+];
+
+#[test]
+fn rewrites_only_the_host() {
+    let registry = ProviderRegistry::default();
+
+    for (link, mirror, expected) in REWRITE_CASES {
+        let url = parse(link).unwrap();
+        let provider = registry.detect(&url).unwrap();
+        let mirror = provider.mirror(mirror).unwrap();
+        assert_eq!(provider.rewrite(&url, mirror).as_str(), *expected, "{link}");
+    }
+}
+
+#[test]
+fn canonicalizes_mirror_links() {
+    let registry = ProviderRegistry::default();
+    let url = parse("https://vxtwitter.com/jack/status/20").unwrap();
+    let provider = registry.find(&url).unwrap();
+
+    assert_eq!(provider.id(), "twitter");
+    assert_eq!(
+        provider.canonicalize(&url).as_str(),
+        "https://twitter.com/jack/status/20"
+    );
+}
+
+#[test]
+fn canonicalizes_twitter_frontends() {
+    let registry = ProviderRegistry::default();
+
+    for link in [
+        "https://fixupx.com/jack/status/20",
+        "https://nitter.poast.org/jack/status/20#m",
+        "https://d.fxtwitter.com/jack/status/20",
+        "https://fxtwitter.com/jack/status/20.jpg",
+        "https://d.vxtwitter.com/jack/status/20.mp4",
+    ] {
+        let url = parse(link).unwrap();
+        let provider = registry.find(&url).unwrap();
+        assert_eq!(provider.id(), "twitter", "{link}");
+        assert_eq!(
+            provider.canonicalize(&url).as_str(),
+            "https://twitter.com/jack/status/20",
+            "{link}"
+        );
+        assert_eq!(provider.post_id(&url).as_deref(), Some("20"), "{link}");
+    }
+
+    // Converting an alias goes straight to the preferred frontend
+    let url = parse("https://fixvx.com/jack/status/20").unwrap();
+    let provider = registry.detect(&url).unwrap();
+    let mirror = registry.preferred_mirror(provider).unwrap();
+    assert_eq!(
+        provider.rewrite(&url, mirror).as_str(),
+        "https://vxtwitter.com/jack/status/20"
+    );
+}
+
+#[test]
+fn canonicalizes_tumblr_blogs() {
+    let registry = ProviderRegistry::default();
+    let url = parse("https://sphene.tumblr.com/post/700000000000000001/pond-studies").unwrap();
+    let provider = registry.detect(&url).unwrap();
+
+    assert_eq!(
+        provider.canonicalize(&url).as_str(),
+        "https://www.tumblr.com/sphene/700000000000000001/pond-studies"
+    );
+    assert_eq!(
+        provider.post_id(&url).as_deref(),
+        Some("sphene/700000000000000001")
+    );
+    // Without a configured mirror links stay on Tumblr
+    assert!(provider.default_mirror().is_none());
+
+    let config = ThoriumConfig::default().with_url("fxtumblr", "https://tumblr.example.com/");
+    let registry = ProviderRegistry::with_builtin_providers(ThoriumClient::default(), &config);
+    let provider = registry.detect(&url).unwrap();
+    let rewritten = provider.rewrite(&url, provider.default_mirror().unwrap());
+    assert_eq!(
+        rewritten.as_str(),
+        "https://tumblr.example.com/sphene/700000000000000001/pond-studies"
+    );
+    assert_eq!(registry.find(&rewritten).unwrap().id(), "tumblr");
+}
+
+#[test]
+fn normalizes_links_without_mirrors() {
+    let registry = ProviderRegistry::default();
+    let cases = [
+        (
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ?feature=share",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+        ),
+        (
+            "https://youtu.be/dQw4w9WgXcQ?si=tracking&t=42",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+        ),
+        (
+            "https://www.twitch.tv/sphene/clip/DuckyPondClip-a1B2c3D4?filter=clips",
+            "https://clips.twitch.tv/DuckyPondClip-a1B2c3D4",
+        ),
+    ];
+
+    for (link, expected) in cases {
+        let url = parse(link).unwrap();
+        let provider = registry.detect(&url).unwrap();
+        let canonical = provider.canonicalize(&url);
+        assert_eq!(canonical.as_str(), expected, "{link}");
+        // The bot has to recognize the links it posted
+        assert_eq!(registry.find(&canonical).unwrap().id(), provider.id());
+    }
+}
+
+#[test]
+fn canonicalizes_instagram_links() {
+    let registry = ProviderRegistry::default();
+    let cases = [
+        (
+            "https://instagram.com/reels/C1a2B3c4D5e/?igsh=MTc4&utm_source=ig_web_copy_link",
+            "https://www.instagram.com/reel/C1a2B3c4D5e/",
+        ),
+        (
+            "https://www.instagram.com/someone/p/C1a2B3c4D5e/?img_index=2&igsh=MTc4",
+            "https://www.instagram.com/p/C1a2B3c4D5e/?img_index=2",
+        ),
+        (
+            "https://www.instagram.com/tv/C1a2B3c4D5e",
+            "https://www.instagram.com/p/C1a2B3c4D5e/",
+        ),
+        (
+            "https://www.instagram.com/stories/someone/3412345678901234567/?utm_source=ig_story_item_share",
+            "https://www.instagram.com/stories/someone/3412345678901234567/",
+        ),
+        (
+            "https://www.instagram.com/share/reel/BAbCdEfGh1/?igsh=MTc4",
+            "https://www.instagram.com/share/reel/BAbCdEfGh1/",
+        ),
+    ];
+
+    for (link, expected) in cases {
+        let url = parse(link).unwrap();
+        let provider = registry.detect(&url).unwrap();
+        assert_eq!(provider.canonicalize(&url).as_str(), expected, "{link}");
+    }
+}
+
+/// Link, expected link without tracking
+const CLEANING_CASES: &[(&str, &str)] = &[
+    (
+        "https://x.com/jack/status/20?s=20&t=abc",
+        "https://x.com/jack/status/20",
+    ),
+    (
+        "https://www.instagram.com/reel/C1a2B3c4D5e/?igsh=MTc4&img_index=2",
+        "https://www.instagram.com/reel/C1a2B3c4D5e/?img_index=2",
+    ),
+    (
+        "https://www.tiktok.com/@scout2015/video/6718335390845095173?_r=1&_t=8abc&is_from_webapp=1&sender_device=pc",
+        "https://www.tiktok.com/@scout2015/video/6718335390845095173",
+    ),
+    // Timestamps are functional
+    (
+        "https://youtu.be/dQw4w9WgXcQ?si=abc123&t=42",
+        "https://youtu.be/dQw4w9WgXcQ?t=42",
+    ),
+    (
+        "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+        "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+    ),
+    // `utm_*` is removed from every link, other parameters are left alone
+    (
+        "https://example.com/article?id=7&utm_source=newsletter&utm_medium=email",
+        "https://example.com/article?id=7",
+    ),
+    (
+        "https://example.com/search?q=a%20b&t=1",
+        "https://example.com/search?q=a%20b&t=1",
+    ),
+];
+
+#[test]
+fn removes_tracking_parameters() {
+    let registry = ProviderRegistry::default();
+
+    for (link, expected) in CLEANING_CASES {
+        let url = parse(link).unwrap();
+        assert_eq!(registry.clean(&url).as_str(), *expected, "{link}");
+    }
+}
+
+#[test]
+fn cleans_only_links_in_text() {
+    let registry = ProviderRegistry::default();
+
+    assert_eq!(
+        registry.clean_links(
+            "what? a=b ||https://x.com/jack/status/20?s=20|| and <https://example.com/?utm_source=x>"
+        ),
+        "what? a=b ||https://x.com/jack/status/20|| and <https://example.com/>"
+    );
+}
+
+#[tokio::test]
+async fn post_keys_survive_edits_of_other_links() {
+    let registry = ProviderRegistry::default();
+    let tweet = parse("https://x.com/jack/status/20?s=20").unwrap();
+    let thread = parse("https://old.reddit.com/r/pics/comments/1gal123/two_views/").unwrap();
+    let tweet_key = registry.post_key(&tweet).await.unwrap();
+    let thread_key = registry.post_key(&thread).await.unwrap();
+    assert_ne!(tweet_key, thread_key);
+
+    let message = "<@1>: https://vxtwitter.com/jack/status/20 and https://rxddit.com/r/pics/comments/1gal123/two_views/";
+    let link = registry.find_post_link(message, &tweet_key).await.unwrap();
+    assert_eq!(link.url.as_str(), "https://vxtwitter.com/jack/status/20");
+
+    // Showing the media of the first link adds links in front of the second one
+    let edited = "<@1>: <https://vxtwitter.com/jack/status/20> (https://pbs.twimg.com/media/a.jpg https://pbs.twimg.com/media/b.jpg) and https://rxddit.com/r/pics/comments/1gal123/two_views/";
+    assert_ne!(find_links(edited)[1].url, find_links(message)[1].url);
+    let link = registry.find_post_link(edited, &thread_key).await.unwrap();
+    assert_eq!(
+        link.url.as_str(),
+        "https://rxddit.com/r/pics/comments/1gal123/two_views/"
+    );
+    let link = registry.find_post_link(edited, &tweet_key).await.unwrap();
+    assert!(link.suppressed);
+}
+
+#[tokio::test]
+async fn generic_links_have_post_keys() {
+    let registry = ProviderRegistry::default();
+    let page = parse("https://example.com/articles/42?utm_source=feed").unwrap();
+    assert!(registry.find(&page).is_none());
+    assert!(registry
+        .fallback()
+        .is_some_and(|fallback| fallback.detect(&page)));
+    let key = registry.post_key(&page).await.unwrap();
+    assert!(key.starts_with("generic_"));
+
+    // Their messages are reposted unchanged, the menu still has to find its link in them
+    let message = "<@1>: look at this https://example.com/articles/42";
+    let link = registry.find_post_link(message, &key).await.unwrap();
+    assert_eq!(link.url.as_str(), "https://example.com/articles/42");
+}
+
+#[test]
+fn rewrites_to_configured_mirrors() {
+    let config = ThoriumConfig::from_toml(
+        r#"
+        [urls]
+        ddinstagram = "https://instagram.example.com/"
+        tiktxk = "http://localhost:8080/tiktok/"
+        "#,
+    )
+    .unwrap();
+    let registry = ProviderRegistry::with_builtin_providers(ThoriumClient::default(), &config);
+
+    let url = parse("https://www.instagram.com/p/C1a2B3c4D5e/").unwrap();
+    let provider = registry.detect(&url).unwrap();
+    assert_eq!(
+        provider
+            .rewrite(&url, provider.default_mirror().unwrap())
+            .as_str(),
+        "https://instagram.example.com/p/C1a2B3c4D5e/"
+    );
+
+    let url = parse("https://www.tiktok.com/@scout2015/video/6718335390845095173").unwrap();
+    let provider = registry.detect(&url).unwrap();
+    let rewritten = provider.rewrite(&url, provider.default_mirror().unwrap());
+    assert_eq!(
+        rewritten.as_str(),
+        "http://localhost:8080/tiktok/@scout2015/video/6718335390845095173"
+    );
+    assert_eq!(
+        registry.find(&rewritten).map(|provider| provider.id()),
+        Some("tiktok")
+    );
+
+    // Mirrors that aren't configured keep their defaults
+    let url = parse("https://bsky.app/profile/bsky.app/post/3l6oveex3ii2l").unwrap();
+    let provider = registry.detect(&url).unwrap();
+    assert_eq!(
+        provider
+            .rewrite(&url, provider.default_mirror().unwrap())
+            .as_str(),
+        "https://bsyy.app/profile/bsky.app/post/3l6oveex3ii2l"
+    );
+}
+
+#[test]
+fn rejects_invalid_config_urls() {
+    assert!(ThoriumConfig::from_toml("[urls]\nvxtwitter = \"not a url\"").is_err());
+    assert!(ThoriumConfig::from_toml("urls = 1").is_err());
+    assert!(ThoriumConfig::from_toml("").unwrap().urls.is_empty());
+}
+
+/// Message, links that should be found in it
+const MESSAGE_CASES: &[(&str, &[&str])] = &[
+    (
+        "look at this https://x.com/jack/status/20",
+        &["https://x.com/jack/status/20"],
+    ),
+    (
+        "<https://x.com/jack/status/20>",
+        &["https://x.com/jack/status/20"],
+    ),
+    (
+        "||https://x.com/jack/status/20||",
+        &["https://x.com/jack/status/20"],
+    ),
+    (
+        "(https://x.com/jack/status/20)",
+        &["https://x.com/jack/status/20"],
+    ),
+    (
+        "https://en.wikipedia.org/wiki/Rust_(programming_language).",
+        &["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+    ),
+    (
+        "two links: https://x.com/a/status/1, https://bsky.app/profile/a/post/b!",
+        &[
+            "https://x.com/a/status/1",
+            "https://bsky.app/profile/a/post/b",
+        ],
+    ),
+    ("what?x=1 is not a link", &[]),
+    ("nothttps://x.com/jack/status/20", &[]),
+    ("ftp://x.com/jack/status/20", &[]),
+];
+
+#[test]
+fn finds_links_in_messages() {
+    for (message, expected) in MESSAGE_CASES {
+        let found: Vec<_> = find_links(message)
+            .into_iter()
+            .map(|link| message[link.range].to_string())
+            .collect();
+        assert_eq!(found, *expected, "{message}");
+    }
+}
+
+#[test]
+fn replaces_only_matched_links() {
+    let registry = ProviderRegistry::default();
+    let message =
+        "https://x.com/a/status/1 and https://example.com/x.com/ stay <https://x.com/b/status/2>";
+
+    let replaced = replace_links(message, |url| {
+        registry
+            .detect(url)
+            .map(|provider| provider.rewrite(url, provider.default_mirror().unwrap()))
+    });
+
+    assert_eq!(
+        replaced,
+        "https://vxtwitter.com/a/status/1 and https://example.com/x.com/ stay <https://vxtwitter.com/b/status/2>"
+    );
+}
+
+#[test]
+fn finds_markdown_around_links() {
+    let message = "||spoiler https://x.com/a/status/1 || <https://x.com/b/status/2> \
+        [the post](https://bsky.app/profile/a/post/b) [quiet](<https://x.com/c/status/3>) https://x.com/d/status/4";
+    let links = find_links(message);
+    let markers: Vec<_> = links
+        .iter()
+        .map(|link| (link.spoiler, link.suppressed, link.label.as_deref()))
+        .collect();
+
+    assert_eq!(
+        markers,
+        [
+            (true, false, None),
+            (false, true, None),
+            (false, false, Some("the post")),
+            (false, true, Some("quiet")),
+            (false, false, None),
+        ]
+    );
+}
+
+#[test]
+fn rewrites_links_keeping_their_markdown() {
+    let registry = ProviderRegistry::default();
+    let message = "||https://x.com/a/status/1|| and [bsky](https://bsky.app/profile/a/post/b) \
+        but not <https://x.com/b/status/2>";
+
+    let rewritten = rewrite_links(message, |link| {
+        let provider = registry.detect(&link.url).filter(|_| !link.suppressed)?;
+        let mirror = provider.default_mirror()?;
+        Some(provider.rewrite(&link.url, mirror).to_string())
+    });
+
+    assert_eq!(
+        rewritten,
+        "||https://vxtwitter.com/a/status/1|| and [bsky](https://bsyy.app/profile/a/post/b) \
+        but not <https://x.com/b/status/2>"
+    );
+}
@@ -1,10 +1,10 @@
+use std::borrow::Cow;
 use std::env;
 
 extern crate dotenv;
 
 use dotenv::dotenv;
 
-use reqwest::Url;
 use serenity::async_trait;
 use serenity::json::Value;
 use serenity::model::application::component::ButtonStyle;
@@ -17,33 +17,117 @@ use serenity::model::prelude::AttachmentType;
 use serenity::model::prelude::ChannelId;
 use serenity::model::prelude::UserId;
 use serenity::prelude::*;
-use thorium::twitter::UrlType;
+use thorium::download::{download_media, Download, DownloadLimits, DEFAULT_MAX_BYTES};
+use thorium::links::find_links;
+use thorium::mosaic::{compose, Mosaic, MosaicConfig};
+use thorium::provider::ProviderRegistry;
+use thorium::{MediaBundle, MediaItem, PostInfo, ThoriumClient, ThoriumConfig};
 
 struct Handler {
     channel_id: ChannelId,
     user_id: UserId,
+    registry: ProviderRegistry,
+}
+
+/// Discord rejects messages with more attachments than this
+const MAX_ATTACHMENTS: usize = 10;
+
+/// Everything that is posted again for a link
+struct Repost {
+    /// Links to the media, the message is only kept as a bookmark when there are none
+    urls: Vec<String>,
+    downloads: Vec<Download>,
+    post: Option<PostInfo>,
+    mosaic: Option<Mosaic>,
+}
+
+/// Author and text of the post, kept with the media for preservation
+fn describe_post(post: &PostInfo) -> String {
+    let author = match (&post.author_name, &post.author_handle) {
+        (Some(name), Some(handle)) => format!("**{}** (@{})", name, handle),
+        (Some(name), None) => format!("**{}**", name),
+        (None, Some(handle)) => format!("**@{}**", handle),
+        (None, None) => String::new(),
+    };
+
+    let mut description = author;
+    if let Some(text) = &post.text {
+        for line in text.lines() {
+            description.push_str("\n> ");
+            description.push_str(line);
+        }
+    }
+    if let Some(created_at) = post.created_at {
+        description.push_str(&format!("\n<t:{}:f>", created_at.timestamp()));
+    }
+    description.trim().to_string()
+}
+
+/// Download the media to attach it, media that doesn't fit in what is left of `budget` is left out
+async fn download_all(
+    client: &ThoriumClient,
+    items: &[MediaItem],
+    mut budget: u64,
+) -> Vec<Download> {
+    let mut downloads = Vec::new();
+    for item in items.iter().take(MAX_ATTACHMENTS) {
+        match download_media(client, item, &DownloadLimits::new(budget)).await {
+            Ok(download) => {
+                budget -= download.size;
+                downloads.push(download);
+            }
+            Err(why) => println!("Error downloading {}: {}", item.url, why),
+        }
+    }
+    downloads
 }
 
 async fn process_tweet(
-    url: String,
+    repost: Repost,
     channel_id: ChannelId,
     msg: Message,
     context: Context,
     target_channel: ChannelId,
 ) {
+    let Repost {
+        urls,
+        downloads,
+        post,
+        mosaic,
+    } = repost;
     if let Err(why) = channel_id
         .send_message(&context.http, |m| {
             m.allowed_mentions(|am| am.empty_parse());
-            if url != "0" {
-                m.add_file(AttachmentType::Image(Url::parse(&url).unwrap()));
+            // The mosaic takes the place of the last image that would fit
+            let images = MAX_ATTACHMENTS - usize::from(mosaic.is_some());
+            for download in downloads.iter().take(images) {
+                m.add_file(AttachmentType::Bytes {
+                    data: Cow::from(download.bytes().unwrap_or_default().to_vec()),
+                    filename: download.file_name.clone(),
+                });
+            }
+            if let Some(mosaic) = &mosaic {
+                m.add_file(AttachmentType::Bytes {
+                    data: Cow::from(mosaic.data.clone()),
+                    filename: mosaic.file_name(),
+                });
             }
             if msg.referenced_message.is_some() {
                 m.reference_message(msg.message_reference.clone().unwrap());
             }
-            if url == "0" {
-                m.content(msg.content.clone());
+            let mut content = if urls.is_empty() {
+                msg.content.clone()
             } else if channel_id == target_channel {
-                m.content(format!("<{}>", msg.content.clone()));
+                format!("<{}>", msg.content.clone())
+            } else {
+                String::new()
+            };
+            if let Some(post) = &post {
+                content.push('\n');
+                content.push_str(&describe_post(post));
+            }
+            if !content.trim().is_empty() {
+                m.content(content.trim());
             }
             m.components(|f| {
                 f.create_action_row(|f| {
@@ -52,7 +136,7 @@ async fn process_tweet(
                             .label("Remove")
                             .style(ButtonStyle::Secondary)
                     });
-                    if url != "0" {
+                    if !urls.is_empty() {
                         f.create_button(|b| {
                             b.custom_id("switch")
                                 .label("Switch")
@@ -75,25 +159,59 @@ async fn process_tweet(
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, context: Context, msg: Message) {
-        if msg.author.id != self.user_id
-            || msg.author.bot
-            || !thorium::bluesky::is_bluesky_url(msg.content.as_str())
-        {
+        if msg.author.id != self.user_id || msg.author.bot {
             return;
         }
 
-        let url: String;
-
-        if thorium::twitter::is_twitter_url(msg.content.as_str()) {
-            url = thorium::twitter::get_media_from_url(
-                thorium::twitter::convert_url_lazy(msg.content.clone(), UrlType::Vxtwitter).await,
-            )
-            .await;
-        } else if thorium::bluesky::is_bluesky_url(msg.content.as_str()) {
-            url = thorium::bluesky::get_media_from_url(msg.content.clone()).await;
-        } else {
-            return;
+        let links = find_links(msg.content.as_str());
+        let mut detected = None;
+        for link in &links {
+            if self.registry.detect_verified(&link.url).await.is_some() {
+                detected = Some(link.url.clone());
+                break;
+            }
+        }
+        // Any other page is bookmarked with what its oEmbed or OpenGraph tags tell about it
+        if detected.is_none() {
+            for link in &links {
+                if self.registry.find_verified(&link.url).await.is_none() {
+                    detected = self
+                        .registry
+                        .fallback()
+                        .filter(|fallback| fallback.detect(&link.url))
+                        .map(|_| link.url.clone());
+                    break;
+                }
+            }
         }
+        let link = match detected {
+            Some(detected) => detected,
+            None => return,
+        };
+
+        // Through the registry, so lookups share its cache and mirror health
+        let bundle = match self.registry.get_media_or_fallback(&link).await {
+            Ok(bundle) => bundle.unwrap_or_default(),
+            Err(why) => {
+                println!("Error getting media: {}", why);
+                MediaBundle::default()
+            }
+        };
+        let urls = bundle.urls().map(str::to_string).collect();
+
+        // Galleries are also kept as a single image, composed here so it doesn't depend on a mirror
+        let mosaic = match compose(self.registry.client(), &bundle, &MosaicConfig::default()).await
+        {
+            Ok(mosaic) => mosaic,
+            Err(why) => {
+                println!("Error composing mosaic: {}", why);
+                None
+            }
+        };
+        // Uploaded by the bot itself, so that files are named after their real format
+        let budget = DEFAULT_MAX_BYTES
+            .saturating_sub(mosaic.as_ref().map_or(0, |mosaic| mosaic.data.len() as u64));
+        let downloads = download_all(self.registry.client(), &bundle.items, budget).await;
 
         let channel_id = if msg.is_private() {
             self.channel_id
@@ -101,8 +219,22 @@ impl EventHandler for Handler {
             msg.channel_id
         };
 
+        let post = match self.registry.get_post_or_fallback(&link).await {
+            Ok(post) => post,
+            Err(why) => {
+                println!("Error getting post: {}", why);
+                None
+            }
+        };
+
+        let repost = Repost {
+            urls,
+            downloads,
+            post,
+            mosaic,
+        };
         process_tweet(
-            url,
+            repost,
             channel_id,
             msg.clone(),
             context.clone(),
@@ -171,8 +303,37 @@ impl EventHandler for Handler {
                 println!("Error deleting message: {:?}", why);
             }
 
+            let (url, bundle) = match self.registry.find_in(msg.content.as_str()) {
+                Some((provider, link)) => {
+                    let link = self.registry.resolve(&link).await.unwrap_or(link);
+                    let url = match self.registry.preferred_mirror(provider) {
+                        Some(mirror) => provider.rewrite(&link, mirror).to_string(),
+                        None => link.to_string(),
+                    };
+                    // The mirror page is only a preview, the media itself comes from the provider
+                    let bundle = match self.registry.get_media(&link).await {
+                        Ok(bundle) => bundle.unwrap_or_default(),
+                        Err(why) => {
+                            println!("Error getting media: {}", why);
+                            MediaBundle::default()
+                        }
+                    };
+                    (url, bundle)
+                }
+                None => (msg.content.clone(), MediaBundle::default()),
+            };
+
+            let downloads =
+                download_all(self.registry.client(), &bundle.items, DEFAULT_MAX_BYTES).await;
+
+            let repost = Repost {
+                urls: vec![url],
+                downloads,
+                post: None,
+                mosaic: None,
+            };
             process_tweet(
-                thorium::twitter::convert_url_lazy(msg.content.clone(), UrlType::Vxtwitter).await,
+                repost,
                 self.channel_id,
                 msg.clone(),
                 ctx,
@@ -196,6 +357,9 @@ async fn main() {
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
     let channel_id = env::var("CHANNEL_ID").expect("Expected a channel id in the environment");
     let user_id = env::var("USER_ID").expect("Expected a user id in the environment");
+    let config = ThoriumConfig::load().expect("invalid thorium config");
+    let http_client =
+        ThoriumClient::new(config.client_config()).expect("failed to build the HTTP client");
     let intents = GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT;
@@ -204,6 +368,7 @@ async fn main() {
         .event_handler(Handler {
             channel_id: ChannelId::from(channel_id.parse::<u64>().unwrap()),
             user_id: UserId::from(user_id.parse::<u64>().unwrap()),
+            registry: ProviderRegistry::with_builtin_providers(http_client, &config),
         })
         .await
         .expect("Err creating client");